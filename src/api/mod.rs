@@ -37,8 +37,6 @@ impl RobloxClient {
             return Err(anyhow!("API request failed: {} - {}", status, text));
         }
 
-        let text = text;
-        
         // Handle empty response (common for PATCH/PUT endpoints)
         if text.is_empty() || text.trim().is_empty() {
             // Try to deserialize from empty JSON object or null
@@ -57,6 +55,14 @@ impl RobloxClient {
         serde_json::from_str(&text).context(format!("Failed to parse response: {}", text))
     }
 
+    // --- Universes ---
+
+    /// Get universe details (displayName, description, ...) via the Open Cloud v2 API
+    pub async fn get_universe(&self, universe_id: u64) -> Result<serde_json::Value> {
+        let url = format!("{}/cloud/v2/universes/{}", BASE_URL, universe_id);
+        self.execute(self.request(Method::GET, &url)).await
+    }
+
     // --- Game Passes ---
 
     pub async fn list_game_passes(&self, universe_id: u64, cursor: Option<String>) -> Result<ListResponse<serde_json::Value>> {
@@ -161,6 +167,54 @@ impl RobloxClient {
         self.execute(self.request(Method::PATCH, &url).multipart(form)).await
     }
 
+    // --- Subscriptions ---
+
+    pub async fn list_subscriptions(&self, universe_id: u64, page_token: Option<String>) -> Result<ListResponse<serde_json::Value>> {
+        let url = format!("{}/subscriptions/v1/universes/{}/subscriptions", BASE_URL, universe_id);
+        let mut req = self.request(Method::GET, &url).query(&[("pageSize", "50")]);
+        if let Some(token) = page_token {
+            req = req.query(&[("pageToken", &token)]);
+        }
+        self.execute(req).await
+    }
+
+    pub async fn create_subscription(&self, universe_id: u64, data: &serde_json::Value) -> Result<serde_json::Value> {
+        let url = format!("{}/subscriptions/v1/universes/{}/subscriptions", BASE_URL, universe_id);
+        log::debug!("Creating subscription at: {}", url);
+        let form = json_to_multipart(data);
+        let result: serde_json::Value = self.execute(self.request(Method::POST, &url).multipart(form)).await?;
+        log::debug!("Create subscription response: {}", result);
+        Ok(result)
+    }
+
+    pub async fn update_subscription(&self, universe_id: u64, subscription_id: u64, data: &serde_json::Value) -> Result<serde_json::Value> {
+        self.update_subscription_with_icon(universe_id, subscription_id, data, None).await
+    }
+
+    /// Update a subscription with an optional image file upload
+    pub async fn update_subscription_with_icon(
+        &self,
+        universe_id: u64,
+        subscription_id: u64,
+        data: &serde_json::Value,
+        image_data: Option<(Vec<u8>, String)>
+    ) -> Result<serde_json::Value> {
+        // Subscription IDs are shown as `EXP-<number>`; the API takes the same form
+        let url = format!("{}/subscriptions/v1/universes/{}/subscriptions/EXP-{}", BASE_URL, universe_id, subscription_id);
+        log::debug!("Updating subscription at URL: {} with data: {}", url, data);
+
+        let mut form = json_to_multipart(data);
+        if let Some((file_bytes, filename)) = image_data {
+            log::debug!("Adding imageFile to form: {} ({} bytes)", filename, file_bytes.len());
+            let file_part = reqwest::multipart::Part::bytes(file_bytes)
+                .file_name(filename)
+                .mime_str("image/png")?;
+            form = form.part("imageFile", file_part);
+        }
+
+        self.execute(self.request(Method::PATCH, &url).multipart(form)).await
+    }
+
     // --- Badges ---
     // Note: Badges API is on badges.roblox.com for v1? The user query says:
     // https://badges.roblox.com/v1/universes/{universeId}/badges
@@ -411,22 +465,276 @@ impl RobloxClient {
         Err(anyhow!("Operation polling timed out after {} attempts", max_attempts))
     }
 
+    // --- DataStores ---
+
+    fn data_store_url(&self, universe_id: u64, path: &str) -> String {
+        format!("{}/datastores/v1/universes/{}/standard-datastores{}", BASE_URL, universe_id, path)
+    }
+
+    /// List the names of the universe's standard DataStores
+    pub async fn list_data_stores(&self, universe_id: u64, prefix: Option<&str>, cursor: Option<String>) -> Result<ListResponse<serde_json::Value>> {
+        let url = self.data_store_url(universe_id, "");
+        let mut req = self.request(Method::GET, &url).query(&[("limit", "100")]);
+        if let Some(p) = prefix {
+            req = req.query(&[("prefix", p)]);
+        }
+        if let Some(c) = cursor {
+            req = req.query(&[("cursor", &c)]);
+        }
+        self.execute(req).await
+    }
+
+    /// List entry keys (`{ "scope", "key" }`) of a DataStore. Without a scope, keys of all scopes are listed.
+    pub async fn list_data_store_entries(
+        &self,
+        universe_id: u64,
+        data_store: &str,
+        scope: Option<&str>,
+        prefix: Option<&str>,
+        cursor: Option<String>,
+    ) -> Result<ListResponse<serde_json::Value>> {
+        let url = self.data_store_url(universe_id, "/datastore/entries");
+        let mut req = self.request(Method::GET, &url).query(&[("datastoreName", data_store), ("limit", "100")]);
+        req = match scope {
+            Some(scope) => req.query(&[("scope", scope)]),
+            None => req.query(&[("allScopes", "true")]),
+        };
+        if let Some(p) = prefix {
+            req = req.query(&[("prefix", p)]);
+        }
+        if let Some(c) = cursor {
+            req = req.query(&[("cursor", &c)]);
+        }
+        self.execute(req).await
+    }
+
+    /// Read a DataStore entry; `None` if the key does not exist
+    pub async fn get_data_store_entry(&self, universe_id: u64, data_store: &str, scope: &str, key: &str) -> Result<Option<DataStoreEntry>> {
+        let url = self.data_store_url(universe_id, "/datastore/entries/entry");
+        let response = self.request(Method::GET, &url)
+            .query(&[("datastoreName", data_store), ("scope", scope), ("entryKey", key)])
+            .send()
+            .await?;
+        let status = response.status();
+        if status == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        let header = |name: &str| response.headers().get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(String::from);
+        let version = header("roblox-entry-version");
+        let content_md5 = header("content-md5");
+        let created_time = header("roblox-entry-created-time");
+        let updated_time = header("roblox-entry-version-created-time");
+        let attributes = header("roblox-entry-attributes").and_then(|a| serde_json::from_str(&a).ok());
+        let user_ids = header("roblox-entry-userids").and_then(|u| serde_json::from_str(&u).ok());
+
+        let text = response.text().await.unwrap_or_default();
+        log::debug!("API response status: {}, body: {}", status, text);
+        if !status.is_success() {
+            return Err(anyhow!("API request failed: {} - {}", status, text));
+        }
+        let value = serde_json::from_str(&text)
+            .with_context(|| format!("DataStore entry '{}' in {} is not JSON", key, data_store))?;
+
+        Ok(Some(DataStoreEntry { value, version, content_md5, created_time, updated_time, attributes, user_ids }))
+    }
+
+    /// Write a DataStore entry, returning the new version (`{ "version", "createdTime", ... }`).
+    /// `attributes` (an object) and `user_ids` (an array) replace the entry's metadata when given.
+    /// The body is sent with its `content-md5` (see `data_store_content_md5`) so Roblox rejects corrupted writes.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn set_data_store_entry(
+        &self,
+        universe_id: u64,
+        data_store: &str,
+        scope: &str,
+        key: &str,
+        value: &serde_json::Value,
+        attributes: Option<&serde_json::Value>,
+        user_ids: Option<&serde_json::Value>,
+    ) -> Result<serde_json::Value> {
+        let url = self.data_store_url(universe_id, "/datastore/entries/entry");
+        let mut req = self.request(Method::POST, &url)
+            .query(&[("datastoreName", data_store), ("scope", scope), ("entryKey", key)])
+            .header("Content-Type", "application/json")
+            .header("content-md5", data_store_content_md5(value)?)
+            .body(serde_json::to_string(value)?);
+        if let Some(a) = attributes {
+            req = req.header("roblox-entry-attributes", a.to_string());
+        }
+        if let Some(u) = user_ids {
+            req = req.header("roblox-entry-userids", u.to_string());
+        }
+        self.execute(req).await
+    }
+
+    // --- Ordered DataStores ---
+
+    fn ordered_data_store_url(&self, universe_id: u64, data_store: &str, scope: &str) -> String {
+        format!("{}/ordered-data-stores/v1/universes/{}/orderedDataStores/{}/scopes/{}/entries",
+            BASE_URL, universe_id, path_segment(data_store), path_segment(scope))
+    }
+
+    /// List entries (`{ "id", "value" }`) of an Ordered DataStore, highest value first when `descending`
+    pub async fn list_ordered_data_store_entries(
+        &self,
+        universe_id: u64,
+        data_store: &str,
+        scope: &str,
+        descending: bool,
+        page_token: Option<String>,
+    ) -> Result<ListResponse<serde_json::Value>> {
+        let url = self.ordered_data_store_url(universe_id, data_store, scope);
+        let mut req = self.request(Method::GET, &url).query(&[("max_page_size", "100")]);
+        if descending {
+            req = req.query(&[("order_by", "desc")]);
+        }
+        if let Some(token) = page_token {
+            req = req.query(&[("page_token", &token)]);
+        }
+        self.execute(req).await
+    }
+
+    pub async fn get_ordered_data_store_entry(&self, universe_id: u64, data_store: &str, scope: &str, id: &str) -> Result<serde_json::Value> {
+        let url = format!("{}/{}", self.ordered_data_store_url(universe_id, data_store, scope), path_segment(id));
+        self.execute(self.request(Method::GET, &url)).await
+    }
+
+    /// Set an entry's value, creating the entry if it does not exist
+    pub async fn update_ordered_data_store_entry(&self, universe_id: u64, data_store: &str, scope: &str, id: &str, value: i64) -> Result<serde_json::Value> {
+        let url = format!("{}/{}", self.ordered_data_store_url(universe_id, data_store, scope), path_segment(id));
+        let req = self.request(Method::PATCH, &url)
+            .query(&[("allow_missing", "true")])
+            .json(&serde_json::json!({ "value": value }));
+        self.execute(req).await
+    }
+
+    pub async fn increment_ordered_data_store_entry(&self, universe_id: u64, data_store: &str, scope: &str, id: &str, amount: i64) -> Result<serde_json::Value> {
+        let url = format!("{}/{}:increment", self.ordered_data_store_url(universe_id, data_store, scope), path_segment(id));
+        self.execute(self.request(Method::POST, &url).json(&serde_json::json!({ "amount": amount }))).await
+    }
+
+    pub async fn delete_ordered_data_store_entry(&self, universe_id: u64, data_store: &str, scope: &str, id: &str) -> Result<()> {
+        let url = format!("{}/{}", self.ordered_data_store_url(universe_id, data_store, scope), path_segment(id));
+        let _: serde_json::Value = self.execute(self.request(Method::DELETE, &url)).await?;
+        Ok(())
+    }
+
+    // --- Messaging ---
+
+    /// Publish a MessagingService message (a string of at most 1 kB) to live servers
+    pub async fn publish_message(&self, universe_id: u64, topic: &str, message: &str) -> Result<()> {
+        let url = format!("{}/messaging-service/v1/universes/{}/topics/{}", BASE_URL, universe_id, path_segment(topic));
+        let _: serde_json::Value = self.execute(self.request(Method::POST, &url).json(&serde_json::json!({ "message": message }))).await?;
+        Ok(())
+    }
+
+    /// Restart the universe's servers. With `all_versions` false, only servers running
+    /// an outdated place version are restarted.
+    pub async fn restart_servers(&self, universe_id: u64, all_versions: bool) -> Result<()> {
+        let url = format!("{}/cloud/v2/universes/{}:restartServers", BASE_URL, universe_id);
+        let body = serde_json::json!({ "closeAllVersions": all_versions });
+        let _: serde_json::Value = self.execute(self.request(Method::POST, &url).json(&body)).await?;
+        Ok(())
+    }
+
+    // --- User Restrictions ---
+
+    fn user_restrictions_url(&self, universe_id: u64, place_id: Option<u64>) -> String {
+        match place_id {
+            Some(place_id) => format!("{}/cloud/v2/universes/{}/places/{}/user-restrictions", BASE_URL, universe_id, place_id),
+            None => format!("{}/cloud/v2/universes/{}/user-restrictions", BASE_URL, universe_id),
+        }
+    }
+
+    /// List the bans of a universe, or of one place
+    pub async fn list_user_restrictions(&self, universe_id: u64, place_id: Option<u64>, page_token: Option<String>) -> Result<ListResponse<serde_json::Value>> {
+        let url = self.user_restrictions_url(universe_id, place_id);
+        let mut req = self.request(Method::GET, &url).query(&[("maxPageSize", "100")]);
+        if let Some(token) = page_token {
+            req = req.query(&[("pageToken", &token)]);
+        }
+        self.execute(req).await
+    }
+
+    /// Set a user's `gameJoinRestriction`; `{ "active": false }` lifts the ban
+    pub async fn update_user_restriction(&self, universe_id: u64, place_id: Option<u64>, user_id: u64, restriction: &serde_json::Value) -> Result<serde_json::Value> {
+        let url = format!("{}/{}", self.user_restrictions_url(universe_id, place_id), user_id);
+        let req = self.request(Method::PATCH, &url)
+            .query(&[("updateMask", "gameJoinRestriction")])
+            .json(&serde_json::json!({ "gameJoinRestriction": restriction }));
+        self.execute(req).await
+    }
+
+    // --- Luau Execution ---
+
+    /// Start running `script` in a server of the place, at the given version or the
+    /// latest one. `timeout_secs` limits how long the script may run (Roblox's default is 5 minutes).
+    pub async fn create_luau_task(&self, universe_id: u64, place_id: u64, version: Option<u64>, script: &str, timeout_secs: Option<u64>) -> Result<LuauTask> {
+        let version_path = version.map(|v| format!("/versions/{}", v)).unwrap_or_default();
+        let url = format!("{}/cloud/v2/universes/{}/places/{}{}/luau-execution-session-tasks", BASE_URL, universe_id, place_id, version_path);
+        let mut body = serde_json::json!({ "script": script });
+        if let Some(secs) = timeout_secs {
+            body["timeout"] = format!("{}s", secs).into();
+        }
+        self.execute(self.request(Method::POST, &url).json(&body)).await
+    }
+
+    /// Current state of a task, by its `path`
+    pub async fn get_luau_task(&self, path: &str) -> Result<LuauTask> {
+        let url = format!("{}/cloud/v2/{}", BASE_URL, path);
+        self.execute(self.request(Method::GET, &url)).await
+    }
+
+    /// Log pages of a task (`{ "structuredMessages": [{ "message", "messageType", ... }] }`)
+    pub async fn list_luau_task_logs(&self, path: &str, page_token: Option<String>) -> Result<ListResponse<serde_json::Value>> {
+        let url = format!("{}/cloud/v2/{}/logs", BASE_URL, path);
+        let mut req = self.request(Method::GET, &url).query(&[("view", "STRUCTURED"), ("maxPageSize", "10000")]);
+        if let Some(token) = page_token {
+            req = req.query(&[("pageToken", &token)]);
+        }
+        self.execute(req).await
+    }
+
     // --- Places ---
 
+    /// Update a place's `displayName`, `description` and/or `serverSize`; only the
+    /// fields listed in `update_mask` are changed. Returns the updated place.
+    pub async fn update_place(&self, universe_id: u64, place_id: u64, data: &serde_json::Value, update_mask: &[&str]) -> Result<serde_json::Value> {
+        let url = format!("{}/cloud/v2/universes/{}/places/{}", BASE_URL, universe_id, place_id);
+        let req = self.request(Method::PATCH, &url)
+            .query(&[("updateMask", update_mask.join(","))])
+            .json(data);
+        self.execute(req).await
+    }
+
+    /// Publish a place file as a new version; the response holds its `versionNumber`
     pub async fn publish_place(&self, universe_id: u64, place_id: u64, file_path: &Path) -> Result<serde_json::Value> {
-        let url = format!("{}/v1/universes/{}/places/{}/versions", BASE_URL, universe_id, place_id);
-        
+        let url = format!("{}/universes/v1/{}/places/{}/versions", BASE_URL, universe_id, place_id);
         let file_content = tokio::fs::read(file_path).await?;
-        let _version_type = "Published"; // or Saved
-        
-        self.client.post(&url)
-            .header("x-api-key", &self.api_key)
+        // .rbxlx files are XML, .rbxl files binary
+        let content_type = match file_path.extension().and_then(|e| e.to_str()) {
+            Some("rbxlx") => "application/xml",
+            _ => "application/octet-stream",
+        };
+
+        let req = self.request(Method::POST, &url)
             .query(&[("versionType", "Published")])
-            .header("Content-Type", "application/octet-stream")
-            .body(file_content)
-            .send()
-            .await?
-            .json().await.map_err(|e| anyhow::anyhow!(e))
+            .header("Content-Type", content_type)
+            .body(file_content);
+        self.execute(req).await
+    }
+
+    /// List the saved and published versions of a place, newest first
+    pub async fn list_place_versions(&self, place_id: u64, page_token: Option<String>) -> Result<ListResponse<serde_json::Value>> {
+        let url = format!("{}/assets/v1/assets/{}/versions", BASE_URL, place_id);
+        let mut req = self.request(Method::GET, &url).query(&[("maxPageSize", "50")]);
+        if let Some(token) = page_token {
+            req = req.query(&[("pageToken", &token)]);
+        }
+        self.execute(req).await
     }
 }
 
@@ -522,6 +830,27 @@ impl RobloxCookieClient {
         serde_json::from_str(&text).context(format!("Failed to parse response: {}", text))
     }
 
+    /// Get universe details (including `rootPlaceId`) via develop.roblox.com API
+    /// Endpoint: GET https://develop.roblox.com/v1/universes/{universeId}
+    pub async fn get_universe_details(&self, universe_id: u64) -> Result<serde_json::Value> {
+        let url = format!("https://develop.roblox.com/v1/universes/{}", universe_id);
+        self.request_with_csrf(Method::GET, &url, None).await
+    }
+
+    /// Update place configuration (e.g. `maxPlayerCount`) via develop.roblox.com API
+    /// Endpoint: PATCH https://develop.roblox.com/v2/places/{placeId}
+    pub async fn update_place_configuration(
+        &self,
+        place_id: u64,
+        settings: &serde_json::Value,
+    ) -> Result<serde_json::Value> {
+        let url = format!("https://develop.roblox.com/v2/places/{}", place_id);
+        log::debug!("Making PATCH request to: {}", url);
+        log::debug!("Request body: {}", settings);
+        
+        self.request_with_csrf(Method::PATCH, &url, Some(settings)).await
+    }
+
     /// Update universe configuration via develop.roblox.com API
     /// Endpoint: PATCH https://develop.roblox.com/v2/universes/{universeId}/configuration
     pub async fn update_universe_configuration(
@@ -537,7 +866,29 @@ impl RobloxCookieClient {
     }
 }
 
+/// Percent-encode everything but unreserved characters, so names containing
+/// `/`, `?`, `#`, spaces or `:` stay a single URL path segment
+fn path_segment(name: &str) -> String {
+    name.bytes()
+        .map(|b| match b {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Base64-encoded MD5 of a DataStore value as `set_data_store_entry` serializes it,
+/// comparable with the `content_md5` Roblox reports for the entry
+pub fn data_store_content_md5(value: &serde_json::Value) -> Result<String> {
+    use base64::Engine;
+    use md5::{Digest, Md5};
+
+    let body = serde_json::to_string(value)?;
+    Ok(base64::engine::general_purpose::STANDARD.encode(Md5::digest(body.as_bytes())))
+}
+
 /// Converts a JSON object to a HashMap suitable for form encoding
+#[allow(dead_code)]
 fn json_to_form(json: &serde_json::Value) -> std::collections::HashMap<String, String> {
     let mut form = std::collections::HashMap::new();
     if let Some(obj) = json.as_object() {
@@ -579,12 +930,49 @@ pub struct ListResponse<T> {
     #[serde(alias = "gamePasses")]
     #[serde(alias = "developerProducts")]
     #[serde(alias = "badges")]
+    #[serde(alias = "subscriptions")]
+    #[serde(alias = "datastores")]
+    #[serde(alias = "keys")]
+    #[serde(alias = "entries")]
+    #[serde(alias = "assetVersions")]
+    #[serde(alias = "luauExecutionSessionTaskLogs")]
+    #[serde(alias = "userRestrictions")]
     pub data: Vec<T>,
     #[serde(alias = "nextPageCursor")]
     #[serde(alias = "nextPageToken")]
     pub next_page_cursor: Option<String>,
 }
 
+/// A Luau execution session task
+#[derive(Debug, Clone, Deserialize)]
+pub struct LuauTask {
+    /// e.g. `universes/1/places/2/versions/3/luau-execution-sessions/.../tasks/...`
+    pub path: String,
+    /// `QUEUED`, `PROCESSING`, `CANCELLED`, `COMPLETE` or `FAILED`
+    #[serde(default)]
+    pub state: String,
+    /// `{ "results": [...] }`: the values the script returned
+    #[serde(default)]
+    pub output: Option<serde_json::Value>,
+    /// `{ "code", "message" }` for failed tasks
+    #[serde(default)]
+    pub error: Option<serde_json::Value>,
+}
+
+/// A DataStore entry's value with the metadata Roblox returns in response headers
+#[derive(Debug, Clone)]
+pub struct DataStoreEntry {
+    pub value: serde_json::Value,
+    pub version: Option<String>,
+    /// Base64-encoded MD5 checksum of the content
+    pub content_md5: Option<String>,
+    pub created_time: Option<String>,
+    /// When the current version was written
+    pub updated_time: Option<String>,
+    pub attributes: Option<serde_json::Value>,
+    pub user_ids: Option<serde_json::Value>,
+}
+
 // Asset upload structs following Asphalt's implementation
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
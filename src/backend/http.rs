@@ -0,0 +1,283 @@
+use super::remote::{ObjectStore, RemoteState};
+use super::{Precondition, RunLock, StateBackend};
+use crate::config::ConfigFormat;
+use crate::lock::LockInfo;
+use crate::state::{StateScope, SyncState};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use reqwest::{Client, Method, StatusCode};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// State stored at a URL: loaded with `GET`, saved with `PUT`.
+///
+/// Any server that returns an `ETag` and honours `If-Match` / `If-None-Match`
+/// on `PUT` gets optimistic concurrency; `404` means no state yet. The run lock
+/// is a `<url>.lock` document created with `If-None-Match: *` and removed with
+/// `DELETE`.
+pub struct HttpBackend {
+    state: RemoteState,
+}
+
+impl HttpBackend {
+    /// `token_env` names an environment variable holding a bearer token
+    pub fn new(url: &str, token_env: Option<&str>, scope: StateScope) -> Result<Self> {
+        let token = match token_env {
+            Some(var) => Some(std::env::var(var)
+                .with_context(|| format!("State backend token variable {} is not set", var))?),
+            None => None,
+        };
+        let url = reqwest::Url::parse(url)
+            .with_context(|| format!("Invalid state backend URL: {}", url))?;
+        // The serialization format follows the URL's extension (YAML by default)
+        let format = ConfigFormat::from_path(Path::new(url.path())).unwrap_or(ConfigFormat::Yaml);
+
+        let store = HttpStore { client: Client::new(), url, token };
+        Ok(Self { state: RemoteState::new(Arc::new(store), format, scope) })
+    }
+}
+
+#[async_trait]
+impl StateBackend for HttpBackend {
+    fn describe(&self) -> String {
+        self.state.describe()
+    }
+
+    async fn load(&mut self) -> Result<SyncState> {
+        self.state.load().await
+    }
+
+    async fn save(&mut self, state: &SyncState) -> Result<()> {
+        self.state.save(state).await
+    }
+
+    async fn claim(&mut self) -> Result<SyncState> {
+        self.state.claim().await
+    }
+
+    async fn lock(&self, command: &str, timeout: Duration) -> Result<RunLock> {
+        self.state.lock(command, timeout).await
+    }
+
+    async fn force_unlock(&self) -> Result<Option<LockInfo>> {
+        self.state.force_unlock().await
+    }
+}
+
+struct HttpStore {
+    client: Client,
+    url: reqwest::Url,
+    token: Option<String>,
+}
+
+impl HttpStore {
+    fn url(&self, suffix: &str) -> reqwest::Url {
+        let mut url = self.url.clone();
+        url.set_path(&format!("{}{}", self.url.path(), suffix));
+        url
+    }
+
+    fn request(&self, method: Method, suffix: &str) -> reqwest::RequestBuilder {
+        let request = self.client.request(method, self.url(suffix));
+        match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+}
+
+#[async_trait]
+impl ObjectStore for HttpStore {
+    fn location(&self, suffix: &str) -> String {
+        self.url(suffix).to_string()
+    }
+
+    async fn get(&self, suffix: &str) -> Result<Option<(String, Precondition)>> {
+        let response = self.request(Method::GET, suffix).send().await?;
+        let status = response.status();
+        if status == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            return Err(anyhow!("Failed to load {}: {} - {}", self.location(suffix), status, text));
+        }
+        let precondition = Precondition::from_etag(response.headers().get(reqwest::header::ETAG));
+        Ok(Some((response.text().await?, precondition)))
+    }
+
+    async fn put(&self, suffix: &str, body: Vec<u8>, precondition: &Precondition) -> Result<Option<Precondition>> {
+        let request = self.request(Method::PUT, suffix).body(body);
+        let response = precondition.apply(request).send().await?;
+        let status = response.status();
+        if status == StatusCode::PRECONDITION_FAILED || status == StatusCode::CONFLICT {
+            return Ok(None);
+        }
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            return Err(anyhow!("Failed to save {}: {} - {}", self.location(suffix), status, text));
+        }
+        Ok(Some(Precondition::from_etag(response.headers().get(reqwest::header::ETAG))))
+    }
+
+    async fn delete(&self, suffix: &str, precondition: &Precondition) -> Result<bool> {
+        let response = precondition.apply(self.request(Method::DELETE, suffix)).send().await?;
+        let status = response.status();
+        if status == StatusCode::PRECONDITION_FAILED || status == StatusCode::CONFLICT {
+            return Ok(false);
+        }
+        if !status.is_success() && status != StatusCode::NOT_FOUND {
+            let text = response.text().await.unwrap_or_default();
+            return Err(anyhow!("Failed to delete {}: {} - {}", self.location(suffix), status, text));
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Minimal stand-in state server: documents keyed by path, with a server-wide write counter as ETag
+    async fn start_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        // (writes so far, path -> (ETag, content))
+        type Documents = (u32, HashMap<String, (u32, String)>);
+        let documents: Arc<Mutex<Documents>> = Arc::new(Mutex::new((0, HashMap::new())));
+
+        tokio::spawn(async move {
+            loop {
+                let Ok((mut socket, _)) = listener.accept().await else { return };
+                let documents = documents.clone();
+                tokio::spawn(async move {
+                    let mut buffer = Vec::new();
+                    let mut chunk = [0u8; 4096];
+                    // Read headers, then the body according to Content-Length
+                    let (head, body) = loop {
+                        let n = socket.read(&mut chunk).await.unwrap();
+                        buffer.extend_from_slice(&chunk[..n]);
+                        let text = String::from_utf8_lossy(&buffer).to_string();
+                        if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                            let length = head.lines()
+                                .find_map(|l| l.to_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse::<usize>().unwrap()))
+                                .unwrap_or(0);
+                            if body.len() >= length {
+                                break (head.to_string(), body.to_string());
+                            }
+                        }
+                    };
+                    let header = |name: &str| head.lines()
+                        .find_map(|l| l.split_once(':').filter(|(k, _)| k.eq_ignore_ascii_case(name)).map(|(_, v)| v.trim().to_string()));
+                    let mut request_line = head.lines().next().unwrap_or_default().split(' ');
+                    let (method, path) = (request_line.next().unwrap_or_default(), request_line.next().unwrap_or_default().to_string());
+
+                    let response = {
+                        let mut guard = documents.lock().unwrap();
+                        let (writes, documents) = &mut *guard;
+                        let current = documents.get(&path).map(|(v, _)| format!("\"{}\"", v));
+                        let allowed = match (header("if-match"), header("if-none-match")) {
+                            (Some(etag), _) => current.as_ref() == Some(&etag),
+                            (_, Some(_)) => current.is_none(),
+                            _ => true,
+                        };
+                        match method {
+                            "GET" => match documents.get(&path) {
+                                Some((version, content)) => format!(
+                                    "HTTP/1.1 200 OK\r\nETag: \"{}\"\r\nContent-Length: {}\r\n\r\n{}", version, content.len(), content
+                                ),
+                                None => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_string(),
+                            },
+                            _ if !allowed => "HTTP/1.1 412 Precondition Failed\r\nContent-Length: 0\r\n\r\n".to_string(),
+                            "DELETE" => {
+                                documents.remove(&path);
+                                "HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n".to_string()
+                            }
+                            _ => {
+                                *writes += 1;
+                                documents.insert(path, (*writes, body));
+                                format!("HTTP/1.1 200 OK\r\nETag: \"{}\"\r\nContent-Length: 0\r\n\r\n", writes)
+                            }
+                        }
+                    };
+                    socket.write_all(response.as_bytes()).await.unwrap();
+                });
+            }
+        });
+
+        format!("http://{}/state/rblxsync-lock.yml", address)
+    }
+
+    #[tokio::test]
+    async fn test_http_backend_round_trip_and_conflicts() {
+        let url = start_server().await;
+
+        let mut first = HttpBackend::new(&url, None, StateScope::new(1, None)).unwrap();
+        let mut state = first.load().await.unwrap();
+        assert!(state.game_passes.is_empty());
+        state.update_game_pass(1, "VIP".to_string(), None, Some(100), None, None, None);
+        first.save(&state).await.unwrap();
+        // Incremental saves keep working with the refreshed ETag
+        state.update_game_pass(2, "Boost".to_string(), None, Some(50), None, None, None);
+        first.save(&state).await.unwrap();
+
+        let mut second = HttpBackend::new(&url, None, StateScope::new(1, None)).unwrap();
+        let mut loaded = second.load().await.unwrap();
+        assert_eq!(loaded.game_passes.len(), 2);
+        loaded.update_badge(3, "Welcome".to_string(), None, None, None, None);
+        second.save(&loaded).await.unwrap();
+
+        // `first` is now behind and must not overwrite `second`'s save
+        let err = first.save(&state).await.unwrap_err();
+        assert!(err.to_string().contains("modified by another run"));
+
+        // A fresh backend that never loaded may not clobber existing state either
+        let mut blind = HttpBackend::new(&url, None, StateScope::new(1, None)).unwrap();
+        assert!(blind.save(&SyncState::new(&StateScope::new(1, None))).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_http_backend_backs_up_upgraded_state() {
+        let url = start_server().await;
+        let legacy = "game_passes:\n  123:\n    name: VIP\n    price: 100\n";
+        Client::new().put(&url).body(legacy).send().await.unwrap();
+        let backup = || async { Client::new().get(format!("{}.v0.bak", url)).send().await.unwrap() };
+
+        // Loading leaves the stored state alone, and the migrated state must be claimed
+        let mut backend = HttpBackend::new(&url, None, StateScope::new(1, None)).unwrap();
+        assert!(backend.load().await.unwrap_err().to_string().contains("state claim"));
+        assert_eq!(backup().await.status(), StatusCode::NOT_FOUND);
+
+        let state = backend.claim().await.unwrap();
+        assert_eq!(state.game_passes[&123].name, "VIP");
+        assert_eq!(backup().await.text().await.unwrap(), legacy);
+        let upgraded = Client::new().get(&url).send().await.unwrap().text().await.unwrap();
+        assert!(upgraded.starts_with(&format!("version: {}", crate::state::STATE_VERSION)));
+    }
+
+    // Releasing the lock on drop blocks on the runtime, which needs worker threads
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_http_backend_lock() {
+        let url = start_server().await;
+        let first = HttpBackend::new(&url, None, StateScope::new(1, None)).unwrap();
+        let second = HttpBackend::new(&url, None, StateScope::new(1, None)).unwrap();
+
+        let lock = first.lock("run", Duration::ZERO).await.unwrap();
+        let err = second.lock("run", Duration::ZERO).await.unwrap_err();
+        assert!(err.to_string().contains("is locked by `run`"));
+
+        drop(lock);
+        let lock = second.lock("publish", Duration::ZERO).await.unwrap();
+        assert_eq!(first.force_unlock().await.unwrap().map(|holder| holder.command), Some("publish".to_string()));
+        assert!(first.force_unlock().await.unwrap().is_none());
+        // The forced-out holder must not delete a lock taken after it
+        let _third = first.lock("run", Duration::ZERO).await.unwrap();
+        drop(lock);
+        assert!(second.lock("run", Duration::ZERO).await.is_err());
+    }
+}
@@ -0,0 +1,149 @@
+//! Storage backends for the sync state (lock file).
+//!
+//! The local lock file next to the config is the default. Teams running
+//! rblxsync from several repositories or CI runners can share state through a
+//! plain HTTP endpoint or an S3-compatible object store instead. Every backend
+//! has a run lock, so two mutating runs never interleave. Remote backends also
+//! use ETags for optimistic concurrency: a save fails if another run saved in
+//! between, instead of silently overwriting its changes.
+
+mod http;
+mod remote;
+mod s3;
+
+pub use http::HttpBackend;
+pub use remote::RemoteLock;
+pub use s3::S3Backend;
+
+use crate::config::{RblxSyncConfig, StateBackendConfig};
+use crate::lock::{LockInfo, StateLock};
+use crate::project::Project;
+use crate::state::{LockFile, StateScope, SyncState};
+use anyhow::Result;
+use async_trait::async_trait;
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[async_trait]
+pub trait StateBackend: Send + Sync {
+    /// Where the state lives, for log messages
+    fn describe(&self) -> String;
+
+    /// Load the stored state for the backend's universe, or an empty state if
+    /// nothing has been stored for it yet
+    async fn load(&mut self) -> Result<SyncState>;
+
+    /// Store the state in its universe's section, keeping the other sections.
+    /// Remote backends fail if the stored state changed since the last
+    /// `load`/`save` by this backend.
+    async fn save(&mut self, state: &SyncState) -> Result<()>;
+
+    /// Assign the state migrated from a single-universe lock file to the
+    /// backend's universe and store it, returning the claimed state
+    async fn claim(&mut self) -> Result<SyncState>;
+
+    /// Take the run lock, waiting up to `timeout` for another run to release it.
+    /// Local files use a lock marker next to them; remote backends a lock object.
+    async fn lock(&self, command: &str, timeout: Duration) -> Result<RunLock>;
+
+    /// Remove the run lock regardless of who holds it, returning the previous holder
+    async fn force_unlock(&self) -> Result<Option<LockInfo>>;
+}
+
+/// A held run lock, released when dropped
+#[derive(Debug)]
+pub enum RunLock {
+    Local(StateLock),
+    Remote(RemoteLock),
+}
+
+/// The default backend: a lock file on disk
+pub struct LocalBackend {
+    path: PathBuf,
+    scope: StateScope,
+}
+
+impl LocalBackend {
+    pub fn new(path: PathBuf, scope: StateScope) -> Self {
+        Self { path, scope }
+    }
+}
+
+#[async_trait]
+impl StateBackend for LocalBackend {
+    fn describe(&self) -> String {
+        self.path.display().to_string()
+    }
+
+    async fn load(&mut self) -> Result<SyncState> {
+        SyncState::load(&self.path, &self.scope)
+    }
+
+    async fn save(&mut self, state: &SyncState) -> Result<()> {
+        state.save(&self.path)
+    }
+
+    async fn claim(&mut self) -> Result<SyncState> {
+        let mut lock_file = LockFile::load(&self.path)?;
+        let state = lock_file.claim(&self.scope)?;
+        lock_file.save(&self.path)?;
+        Ok(state)
+    }
+
+    async fn lock(&self, command: &str, timeout: Duration) -> Result<RunLock> {
+        StateLock::acquire(&self.path, command, timeout).await.map(RunLock::Local)
+    }
+
+    async fn force_unlock(&self) -> Result<Option<LockInfo>> {
+        StateLock::force_unlock(&self.path)
+    }
+}
+
+/// Create the backend selected by the config's `state:` section
+pub fn from_config(config: &RblxSyncConfig, project: &Project) -> Result<Box<dyn StateBackend>> {
+    let scope = config.state_scope();
+    Ok(match &config.state {
+        None | Some(StateBackendConfig::Local) => Box::new(LocalBackend::new(project.lock_file().to_path_buf(), scope)),
+        Some(StateBackendConfig::Http { url, token_env }) => Box::new(HttpBackend::new(url, token_env.as_deref(), scope)?),
+        Some(StateBackendConfig::S3 { bucket, key, region, endpoint }) => {
+            Box::new(S3Backend::new(bucket, key, region, endpoint.as_deref(), scope)?)
+        }
+    })
+}
+
+/// Precondition sent with the next remote write, based on the last seen ETag
+#[derive(Debug, Clone, PartialEq)]
+enum Precondition {
+    /// Nothing stored yet: only create (`If-None-Match: *`)
+    Absent,
+    /// Only overwrite this version (`If-Match`)
+    Matches(String),
+    /// The server did not return an ETag, so writes are unconditional
+    Unchecked,
+}
+
+impl Precondition {
+    fn from_etag(etag: Option<&reqwest::header::HeaderValue>) -> Self {
+        match etag.and_then(|v| v.to_str().ok()) {
+            Some(etag) => Precondition::Matches(etag.to_string()),
+            None => Precondition::Unchecked,
+        }
+    }
+
+    fn apply(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match self {
+            Precondition::Absent => request.header("If-None-Match", "*"),
+            Precondition::Matches(etag) => request.header("If-Match", etag),
+            Precondition::Unchecked => request,
+        }
+    }
+}
+
+/// Error for a failed ETag precondition
+fn conflict_error(location: &str) -> anyhow::Error {
+    anyhow::anyhow!(
+        "State at {} was modified by another run since it was loaded. \
+         Re-run to pick up the latest state.",
+        location
+    )
+}
@@ -0,0 +1,203 @@
+//! Parts shared by the remote backends: loading and saving the state object
+//! with ETag preconditions, and the run lock kept in a `<object>.lock` object
+//! next to it.
+
+use super::{conflict_error, Precondition, RunLock};
+use crate::config::ConfigFormat;
+use crate::lock::{LockInfo, POLL_INTERVAL};
+use crate::state::{LockFile, StateScope, SyncState, STATE_VERSION};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use log::{info, warn};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Suffix of the lock object, appended to the state object's location
+const LOCK_SUFFIX: &str = ".lock";
+
+/// Objects at the state's location plus a suffix (`""` for the state itself)
+#[async_trait]
+pub(super) trait ObjectStore: Send + Sync {
+    /// URL of the object, for messages
+    fn location(&self, suffix: &str) -> String;
+
+    /// Read an object and the precondition for overwriting exactly this version;
+    /// `None` if it does not exist
+    async fn get(&self, suffix: &str) -> Result<Option<(String, Precondition)>>;
+
+    /// Write an object if `precondition` holds. Returns the precondition for
+    /// the next write, or `None` if the precondition failed.
+    async fn put(&self, suffix: &str, body: Vec<u8>, precondition: &Precondition) -> Result<Option<Precondition>>;
+
+    /// Delete an object if `precondition` holds; false if it failed
+    async fn delete(&self, suffix: &str, precondition: &Precondition) -> Result<bool>;
+}
+
+/// The state document of a remote backend
+pub(super) struct RemoteState {
+    store: Arc<dyn ObjectStore>,
+    format: ConfigFormat,
+    precondition: Precondition,
+    scope: StateScope,
+    /// The whole lock file as last loaded or saved, so saves keep other universes' sections
+    document: LockFile,
+}
+
+impl RemoteState {
+    pub fn new(store: Arc<dyn ObjectStore>, format: ConfigFormat, scope: StateScope) -> Self {
+        Self {
+            store,
+            format,
+            precondition: Precondition::Absent,
+            scope,
+            document: LockFile::default(),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        self.store.location("")
+    }
+
+    pub async fn load(&mut self) -> Result<SyncState> {
+        self.fetch().await?;
+        self.document.section(&self.scope)
+    }
+
+    pub async fn save(&mut self, state: &SyncState) -> Result<()> {
+        self.document.set_section(state)?;
+        self.write().await
+    }
+
+    pub async fn claim(&mut self) -> Result<SyncState> {
+        self.fetch().await?;
+        let state = self.document.claim(&self.scope)?;
+        self.write().await?;
+        Ok(state)
+    }
+
+    /// Read the state object into `document`
+    async fn fetch(&mut self) -> Result<()> {
+        let Some((content, precondition)) = self.store.get("").await? else {
+            self.precondition = Precondition::Absent;
+            self.document = LockFile::default();
+            return Ok(());
+        };
+
+        self.precondition = precondition;
+        // Older schemas are upgraded in memory; the next save backs up the
+        // original and writes the new version
+        let (document, _) = LockFile::parse(self.format, &content)
+            .with_context(|| format!("Failed to parse state from {}", self.describe()))?;
+        self.document = document;
+        Ok(())
+    }
+
+    /// Write `document` to the state object, if it was not changed since it was read
+    async fn write(&mut self) -> Result<()> {
+        if let Some((suffix, original)) = self.document.take_backup() {
+            // An existing backup is from an earlier attempt at the same upgrade; keep it
+            self.store.put(&suffix, original.into_bytes(), &Precondition::Absent).await
+                .with_context(|| format!("Failed to back up state to {}", self.store.location(&suffix)))?;
+            info!(
+                "Upgrading state at {} to version {} (backup: {})",
+                self.describe(), STATE_VERSION, self.store.location(&suffix)
+            );
+        }
+        let content = self.format.serialize(&self.document)?.into_bytes();
+        let precondition = self.store.put("", content, &self.precondition).await?
+            .ok_or_else(|| conflict_error(&self.describe()))?;
+        if precondition == Precondition::Unchecked {
+            warn!("State server at {} returned no ETag; concurrent saves will not be detected", self.describe());
+        }
+        self.precondition = precondition;
+        Ok(())
+    }
+
+    /// Take the run lock by creating the lock object with `If-None-Match: *`,
+    /// waiting up to `timeout` for another run to release it. Stale locks are
+    /// removed, but only if they were not replaced since they were read.
+    pub async fn lock(&self, command: &str, timeout: Duration) -> Result<RunLock> {
+        let info = LockInfo::current(command);
+        let location = self.store.location(LOCK_SUFFIX);
+        let started = Instant::now();
+        let mut announced = false;
+
+        loop {
+            let body = serde_json::to_vec(&info)?;
+            if let Some(precondition) = self.store.put(LOCK_SUFFIX, body, &Precondition::Absent).await? {
+                return Ok(RunLock::Remote(RemoteLock { store: self.store.clone(), precondition, info }));
+            }
+
+            // Released between our create attempt and the read; retry immediately
+            let Some((content, precondition)) = self.store.get(LOCK_SUFFIX).await? else { continue };
+            // The lock object is written in one request, so an unreadable one is
+            // not half-written; it stays held until it is force-unlocked
+            let holder = serde_json::from_str::<LockInfo>(&content).ok();
+            if let Some(holder) = holder.as_ref().filter(|h| h.is_stale()) {
+                warn!("Removing stale lock held by {}", holder.describe());
+                self.store.delete(LOCK_SUFFIX, &precondition).await?;
+                continue;
+            }
+
+            let described = holder.map(|h| h.describe()).unwrap_or_else(|| "an unreadable lock".to_string());
+            if started.elapsed() >= timeout {
+                return Err(anyhow!(
+                    "State at {} is locked by {} ({}). Wait for it to finish, pass --lock-timeout to wait longer, \
+                     or run `rblxsync force-unlock` if that run is no longer active.",
+                    self.describe(), described, location
+                ));
+            }
+            if !announced {
+                info!("Waiting for lock held by {}...", described);
+                announced = true;
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    /// Delete the lock object regardless of who holds it, returning the previous holder
+    pub async fn force_unlock(&self) -> Result<Option<LockInfo>> {
+        let Some((content, _)) = self.store.get(LOCK_SUFFIX).await? else { return Ok(None) };
+        self.store.delete(LOCK_SUFFIX, &Precondition::Unchecked).await?;
+        Ok(Some(serde_json::from_str(&content).unwrap_or_else(|_| LockInfo::unknown(0))))
+    }
+}
+
+/// A held remote run lock; the lock object is deleted on drop
+pub struct RemoteLock {
+    store: Arc<dyn ObjectStore>,
+    /// Matches the lock object as we created it, so a lock that was
+    /// force-unlocked and taken by another run is not deleted
+    precondition: Precondition,
+    info: LockInfo,
+}
+
+impl std::fmt::Debug for RemoteLock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RemoteLock")
+            .field("location", &self.store.location(LOCK_SUFFIX))
+            .field("info", &self.info)
+            .finish()
+    }
+}
+
+impl Drop for RemoteLock {
+    fn drop(&mut self) {
+        let store = self.store.clone();
+        let precondition = self.precondition.clone();
+        let release = async move { store.delete(LOCK_SUFFIX, &precondition).await };
+
+        // Drop can't await, so block this worker thread on the release
+        let released = match tokio::runtime::Handle::try_current() {
+            Ok(handle) if handle.runtime_flavor() == tokio::runtime::RuntimeFlavor::MultiThread => {
+                tokio::task::block_in_place(|| handle.block_on(release))
+            }
+            _ => Err(anyhow!("no multi-threaded runtime is running")),
+        };
+        match released {
+            Ok(true) => {}
+            Ok(false) => warn!("Lock {} was taken over by another run; leaving it in place", self.store.location(LOCK_SUFFIX)),
+            Err(e) => warn!("Failed to release lock {}: {}. Run `rblxsync force-unlock` if it is left behind.", self.store.location(LOCK_SUFFIX), e),
+        }
+    }
+}
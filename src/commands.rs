@@ -1,12 +1,17 @@
-use crate::api::{RobloxClient, RobloxCookieClient};
-use crate::config::{RblxSyncConfig, PrivateServerCost};
+use crate::backend::StateBackend;
+use crate::api::{data_store_content_md5, ListResponse, RobloxClient, RobloxCookieClient};
+use crate::config::{BadgeConfig, ConfigFormat, CreatorConfig, DeveloperProductConfig, GamePassConfig, PlaceConfig, PrivateServerCost, RblxSyncConfig, SubscriptionConfig, SubscriptionPeriod};
+use crate::exec;
+use crate::history::{History, Snapshot};
 use crate::output;
-use crate::state::{SyncState, ResourceState, UniverseState};
+use crate::place;
+use crate::project::Project;
+use crate::state::{DataStoreEntryState, LockFile, PlaceState, SyncState, ResourceKind, ResourceState, UniverseState, UserRestrictionState};
 use anyhow::{anyhow, Result};
 use log::{info, warn, error};
 use sha2::{Digest, Sha256};
 use std::path::Path;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
 /// Validate the configuration for errors (including case-insensitive duplicate names)
 pub fn validate(config: &RblxSyncConfig) -> Result<()> {
@@ -21,44 +26,143 @@ pub fn validate(config: &RblxSyncConfig) -> Result<()> {
     // Check for duplicate badge names (case-insensitive)
     let badge_names: Vec<&str> = config.badges.iter().map(|b| b.name.as_str()).collect();
     check_for_duplicates(&badge_names, "badge")?;
+
+    // Check for duplicate subscription names (case-insensitive)
+    let subscription_names: Vec<&str> = config.subscriptions.iter().map(|s| s.name.as_str()).collect();
+    check_for_duplicates(&subscription_names, "subscription")?;
+    for subscription in &config.subscriptions {
+        let valid_tier = subscription.price_tier.split_once('.')
+            .is_some_and(|(dollars, cents)| !dollars.is_empty() && dollars.chars().all(|c| c.is_ascii_digit())
+                && cents.len() == 2 && cents.chars().all(|c| c.is_ascii_digit()));
+        if !valid_tier {
+            return Err(anyhow!("Subscription '{}' has an invalid price_tier '{}' (expected a USD amount such as \"4.99\")", subscription.name, subscription.price_tier));
+        }
+    }
+
+    // DataStore keys are case-sensitive, so only exact duplicates are an error
+    let mut data_store_keys = HashSet::new();
+    for store in &config.data_stores {
+        for entry in &store.entries {
+            if entry.value.is_some() == entry.file.is_some() {
+                return Err(anyhow!("DataStore entry '{}' in {} must set exactly one of `value` or `file`", entry.key, store.name));
+            }
+            if !data_store_keys.insert((&store.name, &store.scope, &entry.key)) {
+                return Err(anyhow!("Duplicate DataStore entry '{}' in {} (scope {})", entry.key, store.name, store.scope));
+            }
+        }
+    }
+
+    for place in &config.places {
+        if place.publish && place.file_path.is_none() {
+            return Err(anyhow!("Place {} has publish: true but no file_path", place.place_id));
+        }
+    }
+
+    let mut restricted = HashSet::new();
+    for restriction in &config.user_restrictions {
+        restriction.to_state()?;
+        if !restricted.insert((restriction.user_id, restriction.place_id)) {
+            match restriction.place_id {
+                Some(place_id) => return Err(anyhow!("Duplicate user restriction for user {} in place {}", restriction.user_id, place_id)),
+                None => return Err(anyhow!("Duplicate user restriction for user {}", restriction.user_id)),
+            }
+        }
+    }
+
+    let mut ordered_names = HashSet::new();
+    for store in &config.ordered_data_stores {
+        if !ordered_names.insert(&store.name) {
+            return Err(anyhow!("Duplicate ordered DataStore '{}'", store.name));
+        }
+    }
     
     Ok(())
 }
 
-pub async fn run(config: RblxSyncConfig, mut state: SyncState, client: RobloxClient, cookie_client: Option<RobloxCookieClient>, dry_run: bool) -> Result<()> {
+/// Print the config file. With `expanded`, print it after `generate:`, defaults
+/// and templates have been resolved, exactly as `run` sees it.
+pub fn show_config(project: &Project, expanded: bool) -> Result<()> {
+    let path = project.config_path();
+    if expanded {
+        let config = RblxSyncConfig::load(path)?;
+        print!("{}", ConfigFormat::from_path(path)?.serialize(&config)?);
+    } else {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read config file at {:?}: {}", path, e))?;
+        print!("{}", content);
+    }
+    Ok(())
+}
+
+pub async fn run(config: RblxSyncConfig, project: &Project, mut state: SyncState, backend: &mut dyn StateBackend, client: RobloxClient, cookie_client: Option<RobloxCookieClient>, dry_run: bool) -> Result<()> {
     info!("Starting sync... (dry_run: {})", dry_run);
 
     // Validate config before proceeding
     validate(&config)?;
     
     let universe_id = config.universe.id;
+    let initial_state = state.clone();
 
     // Update Universe Settings (requires cookie client)
     if config.universe.has_settings() {
         if let Some(ref cookie_client) = cookie_client {
-            sync_universe_settings(universe_id, &config, &mut state, cookie_client, dry_run).await?;
+            sync_universe_settings(universe_id, &config, &mut state, backend, cookie_client, dry_run).await?;
         }
     }
+    if config.has_place_settings() {
+        info!("Syncing Place Settings...");
+        sync_place_settings(universe_id, &config, &mut state, backend, &client, dry_run).await?;
+    }
 
     // 2. Sync Resources
-    sync_game_passes(universe_id, &config, &mut state, &client, dry_run).await?;
-    sync_developer_products(universe_id, &config, &mut state, &client, dry_run).await?;
-    sync_badges(universe_id, &config, &mut state, &client, dry_run).await?;
+    sync_game_passes(universe_id, &config, project, &mut state, backend, &client, dry_run).await?;
+    sync_developer_products(universe_id, &config, project, &mut state, backend, &client, dry_run).await?;
+    sync_badges(universe_id, &config, project, &mut state, backend, &client, dry_run).await?;
+    if !config.subscriptions.is_empty() {
+        sync_subscriptions(universe_id, &config, project, &mut state, backend, &client, dry_run).await?;
+    }
+    if !config.data_stores.is_empty() {
+        sync_data_stores(universe_id, &config, project, &mut state, backend, &client, dry_run).await?;
+    }
+    if !config.user_restrictions.is_empty() || !state.user_restrictions.is_empty() {
+        sync_user_restrictions(universe_id, &config, &mut state, backend, &client, dry_run).await?;
+    }
 
-    // Save state
+    // Save state (mutations are already persisted as they happen; this also
+    // records resources that were linked by name without changes)
     if !dry_run {
-        let root = std::env::current_dir()?;
-        state.save(&root)?;
+        backend.save(&state).await?;
     } else {
         info!("Dry Run: Would save state.");
     }
 
+    if !dry_run {
+        let snapshot = History::for_project(project, config.state_scope()).record(project, "run", &state)?;
+        info!("Recorded history snapshot {}", snapshot.id);
+    }
+
     // Generate output config file if output_path is specified
     if let Some(output_path) = &config.output_path {
+        let output_path = project.resolve(output_path);
         if dry_run {
-            info!("Dry Run: Would generate config file at {}", output_path);
+            info!("Dry Run: Would generate config file at {}", output_path.display());
         } else {
-            output::generate_config(&state, config.universe.id, output_path)?;
+            output::generate_config(&state, config.universe.id, &output_path)?;
+        }
+    }
+
+    if let Some(notify) = &config.notify {
+        // Dry runs don't change the state, so the changes are not known here
+        match changed_resources(universe_id, &initial_state, &state) {
+            _ if dry_run => info!("Dry Run: Would publish the changed resources to topic '{}'", notify.topic),
+            None => info!("No changes to announce on topic '{}'", notify.topic),
+            Some(message) => {
+                // The sync itself succeeded, so a failed announcement only warns
+                match client.publish_message(universe_id, &notify.topic, &message).await {
+                    Ok(()) => info!("Published the changed resources to topic '{}'", notify.topic),
+                    Err(e) => warn!("Failed to publish to topic '{}': {}", notify.topic, e),
+                }
+            }
         }
     }
 
@@ -66,27 +170,148 @@ pub async fn run(config: RblxSyncConfig, mut state: SyncState, client: RobloxCli
     Ok(())
 }
 
-pub async fn publish(config: RblxSyncConfig, client: RobloxClient) -> Result<()> {
+/// MessagingService messages are limited to 1 kB
+const MESSAGE_LIMIT: usize = 1024;
+
+/// Publish a JSON message to a MessagingService topic
+pub async fn message_publish(universe_id: u64, topic: &str, message: &str, client: &RobloxClient) -> Result<()> {
+    let message: serde_json::Value = serde_json::from_str(message)
+        .map_err(|e| anyhow!("Message is not valid JSON: {}", e))?;
+    let message = message.to_string();
+    if message.len() > MESSAGE_LIMIT {
+        return Err(anyhow!("Message is {} bytes; MessagingService allows at most {}", message.len(), MESSAGE_LIMIT));
+    }
+    client.publish_message(universe_id, topic, &message).await?;
+    info!("Published message to topic '{}'", topic);
+    Ok(())
+}
+
+/// The `notify:` message for a run: the IDs of the resources it created or changed,
+/// or `None` if nothing changed. Resources only linked by name are not announced.
+/// If the IDs don't fit in one message, only `truncated` is sent and servers should
+/// reload everything.
+fn changed_resources(universe_id: u64, before: &SyncState, after: &SyncState) -> Option<String> {
+    let mut changed = serde_json::Map::new();
+    if before.universe != after.universe {
+        changed.insert("universe".to_string(), true.into());
+    }
+    for kind in ResourceKind::ALL {
+        let ids: Vec<u64> = after.resources(kind).iter()
+            .filter(|(id, resource)| match before.resources(kind).get(id) {
+                Some(previous) => previous.fingerprint() != resource.fingerprint() || previous.icon_hash != resource.icon_hash,
+                // Newly tracked: created or updated by this run, rather than linked by name
+                None => resource.last_synced_at.is_some(),
+            })
+            .map(|(id, _)| *id)
+            .collect();
+        if !ids.is_empty() {
+            changed.insert(kind.section().to_string(), ids.into());
+        }
+    }
+    let places: Vec<u64> = after.places.iter()
+        .filter(|(id, place)| before.places.get(id) != Some(place))
+        .map(|(id, _)| *id)
+        .collect();
+    if !places.is_empty() {
+        changed.insert("places".to_string(), places.into());
+    }
+    let entries: Vec<String> = after.data_stores.iter()
+        .flat_map(|(store, entries)| entries.iter().map(move |(entry, state)| (store, entry, state)))
+        .filter(|(store, entry, state)| {
+            before.data_stores.get(*store).and_then(|e| e.get(*entry)).is_none_or(|previous| previous.hash != state.hash)
+        })
+        .map(|(store, entry, _)| format!("{}/{}", store, entry))
+        .collect();
+    if !entries.is_empty() {
+        changed.insert("data_stores".to_string(), entries.into());
+    }
+
+    if changed.is_empty() {
+        return None;
+    }
+    let message = serde_json::json!({ "universe_id": universe_id, "changed": changed }).to_string();
+    if message.len() <= MESSAGE_LIMIT {
+        Some(message)
+    } else {
+        Some(serde_json::json!({ "universe_id": universe_id, "truncated": true }).to_string())
+    }
+}
+
+/// How `publish` restarts servers after the places are published
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RestartOptions {
+    /// Restart even if no place sets `restart_servers`
+    pub restart: bool,
+    /// Only restart servers running an outdated version
+    pub outdated_only: bool,
+}
+
+pub async fn publish(config: RblxSyncConfig, project: &Project, state: &mut SyncState, backend: &mut dyn StateBackend, client: RobloxClient, restart: RestartOptions) -> Result<()> {
     let universe_id = config.universe.id;
+    let mut published = 0;
+    let mut failed = 0;
+    let mut script_failures = 0;
 
-    for place in config.places {
+    for place in &config.places {
         if place.publish {
-            info!("Publishing place {} from {}", place.place_id, place.file_path);
-            let path = Path::new(&place.file_path);
+            let Some(file_path) = &place.file_path else {
+                error!("Place {} has publish: true but no file_path", place.place_id);
+                failed += 1;
+                continue;
+            };
+            let path = project.resolve(file_path);
+            info!("Publishing place {} from {}", place.place_id, path.display());
             if !path.exists() {
-                error!("File not found: {}", place.file_path);
+                error!("File not found: {}", path.display());
+                failed += 1;
                 continue;
             }
-            match client.publish_place(universe_id, place.place_id, path).await {
-                Ok(_) => info!("Published place {}", place.place_id),
-                Err(e) => error!("Failed to publish place {}: {}", place.place_id, e),
+            match place::publish_version(universe_id, place.place_id, &path, None, project, state, backend, &client).await {
+                Ok(version) => {
+                    info!("Published place {} (version {})", place.place_id, version);
+                    published += 1;
+                    if let Some(script) = &place.post_publish_script {
+                        if let Err(e) = exec::run_script(&client, universe_id, place.place_id, Some(version), &project.resolve(script), None).await {
+                            error!("Post-publish script for place {} failed: {}", place.place_id, e);
+                            script_failures += 1;
+                        }
+                    }
+                }
+                Err(e) => {
+                    error!("Failed to publish place {}: {}", place.place_id, e);
+                    failed += 1;
+                }
             }
         }
     }
+
+    if script_failures > 0 {
+        if restart.restart || config.places.iter().any(|p| p.publish && p.restart_servers) {
+            warn!("Not restarting servers: post-publish scripts failed");
+        }
+        return Err(anyhow!("{} post-publish scripts failed", script_failures));
+    }
+
+    let restarting: Vec<_> = config.places.iter().filter(|p| p.publish && p.restart_servers).collect();
+    if !restart.restart && restarting.is_empty() {
+        return Ok(());
+    }
+    // Servers would otherwise be moved to a mix of new and old place versions
+    if failed > 0 || published == 0 {
+        warn!("Not restarting servers: {} of {} places failed to publish", failed, published + failed);
+        return Ok(());
+    }
+    let outdated_only = restart.outdated_only || restarting.iter().any(|p| p.restart_outdated_only);
+    client.restart_servers(universe_id, !outdated_only).await?;
+    if outdated_only {
+        info!("Restarted servers running outdated versions of universe {}", universe_id);
+    } else {
+        info!("Restarted all servers of universe {}", universe_id);
+    }
     Ok(())
 }
 
-async fn sync_universe_settings(universe_id: u64, config: &RblxSyncConfig, state: &mut SyncState, cookie_client: &RobloxCookieClient, dry_run: bool) -> Result<()> {
+async fn sync_universe_settings(universe_id: u64, config: &RblxSyncConfig, state: &mut SyncState, backend: &mut dyn StateBackend, cookie_client: &RobloxCookieClient, dry_run: bool) -> Result<()> {
     info!("Syncing Universe Settings...");
     
     // Build the current desired state from config
@@ -106,22 +331,19 @@ async fn sync_universe_settings(universe_id: u64, config: &RblxSyncConfig, state
         private_server_cost: private_server_cost_state.clone(),
     };
     
-    // Check for diffs against stored state
-    let stored_state = state.universe.as_ref();
-    let mut changes: Vec<&str> = Vec::new();
-    
-    if stored_state.map(|s| &s.name) != Some(&desired_state.name) && desired_state.name.is_some() {
-        changes.push("name");
-    }
-    if stored_state.map(|s| &s.description) != Some(&desired_state.description) && desired_state.description.is_some() {
-        changes.push("description");
-    }
-    if stored_state.map(|s| &s.playable_devices) != Some(&desired_state.playable_devices) && desired_state.playable_devices.is_some() {
-        changes.push("playable_devices");
-    }
-    if stored_state.map(|s| &s.private_server_cost) != Some(&desired_state.private_server_cost) && desired_state.private_server_cost.is_some() {
-        changes.push("private_server_cost");
+    // The universe's max_players is the start place's server size, so it can't also be set on that place
+    if desired_state.max_players.is_some() && config.places.iter().any(|p| p.max_players.is_some()) {
+        let root_place_id = root_place_id(universe_id, cookie_client).await?;
+        if config.places.iter().any(|p| p.place_id == root_place_id && p.max_players.is_some()) {
+            return Err(anyhow!(
+                "max_players is set both under universe and under places for the start place {}; set it in only one of them",
+                root_place_id
+            ));
+        }
     }
+
+    // Check for diffs against stored state
+    let changes = universe_changes(state.universe.as_ref(), &desired_state);
     
     let has_changes = !changes.is_empty();
     
@@ -130,77 +352,13 @@ async fn sync_universe_settings(universe_id: u64, config: &RblxSyncConfig, state
         return Ok(());
     }
     
-    // Build the request body for develop.roblox.com/v2/universes/{id}/configuration
-    let mut body = serde_json::Map::new();
-    
-    // Add fields that are changing
-    if changes.contains(&"name") {
-        if let Some(name) = &desired_state.name {
-            body.insert("name".to_string(), name.clone().into());
-        }
-    }
-    if changes.contains(&"description") {
-        if let Some(desc) = &desired_state.description {
-            body.insert("description".to_string(), desc.clone().into());
-        }
-    }
-    
-    // Map playable devices to numeric array (1=Computer, 2=Phone, 3=Tablet, 4=Console, 5=VR)
-    if changes.contains(&"playable_devices") {
-        if let Some(devices) = &desired_state.playable_devices {
-            let device_ids: Vec<u8> = devices.iter().filter_map(|d| {
-                match d.to_lowercase().as_str() {
-                    "computer" => Some(1),
-                    "phone" => Some(2),
-                    "tablet" => Some(3),
-                    "console" => Some(4),
-                    "vr" => Some(5),
-                    _ => None,
-                }
-            }).collect();
-            body.insert("playableDevices".to_string(), serde_json::json!(device_ids));
-        }
-    }
-    
-    // Handle private server cost
-    if changes.contains(&"private_server_cost") {
-        if let Some(cost) = &config.universe.private_server_cost {
-            match cost {
-                PrivateServerCost::Disabled => {
-                    body.insert("allowPrivateServers".to_string(), serde_json::json!(false));
-                }
-                PrivateServerCost::Free => {
-                    body.insert("allowPrivateServers".to_string(), serde_json::json!(true));
-                    body.insert("privateServerPrice".to_string(), serde_json::json!(0));
-                }
-                PrivateServerCost::Paid(price) => {
-                    body.insert("allowPrivateServers".to_string(), serde_json::json!(true));
-                    body.insert("privateServerPrice".to_string(), serde_json::json!(price));
-                }
-            }
-        }
-    }
-    
     if dry_run {
         info!("  [UPDATE] Universe Settings - would update: {}", changes.join(", "));
         info!("  Dry Run: Would PATCH to https://develop.roblox.com/v2/universes/{}/configuration", universe_id);
     } else {
-        info!("  Request URL: https://develop.roblox.com/v2/universes/{}/configuration", universe_id);
-        info!("  Request Body: {}", serde_json::to_string_pretty(&serde_json::Value::Object(body.clone())).unwrap_or_default());
-        let response = cookie_client.update_universe_configuration(universe_id, &serde_json::Value::Object(body)).await?;
-        
-        // Output raw response
-        info!("  Universe API Response: {}", serde_json::to_string_pretty(&response).unwrap_or_else(|_| response.to_string()));
-        
-        // Update state after successful sync
-        state.update_universe(
-            desired_state.name.clone(),
-            desired_state.description.clone(),
-            desired_state.genre.clone(),
-            desired_state.playable_devices.clone(),
-            desired_state.max_players,
-            desired_state.private_server_cost.clone(),
-        );
+        // Only record what Roblox confirmed, so the lock file never claims a value it didn't accept
+        apply_universe_changes(universe_id, state, &desired_state, &changes, cookie_client).await?;
+        backend.save(state).await?;
         
         info!("  [UPDATED] Universe Settings - updated: {}", changes.join(", "));
     }
@@ -208,7 +366,71 @@ async fn sync_universe_settings(universe_id: u64, config: &RblxSyncConfig, state
     Ok(())
 }
 
-async fn sync_game_passes(universe_id: u64, config: &RblxSyncConfig, state: &mut SyncState, client: &RobloxClient, dry_run: bool) -> Result<()> {
+/// Sync per-place settings (name, description and server size) for the configured
+/// places, whether or not their files are published
+async fn sync_place_settings(universe_id: u64, config: &RblxSyncConfig, state: &mut SyncState, backend: &mut dyn StateBackend, client: &RobloxClient, dry_run: bool) -> Result<()> {
+    for place in &config.places {
+        let stored = state.places.get(&place.place_id).cloned().unwrap_or_default();
+        let changes = place_changes(&stored, place);
+        if changes.is_empty() {
+            if place.name.is_some() || place.description.is_some() || place.max_players.is_some() {
+                info!("  [SKIP] Place {} - no changes detected", place.place_id);
+            }
+            continue;
+        }
+        let fields: Vec<&str> = changes.iter().map(|(field, _)| *field).collect();
+        if dry_run {
+            info!("  [UPDATE] Place {} - would update: {}", place.place_id, fields.join(", "));
+            continue;
+        }
+
+        let mut patch = serde_json::Map::new();
+        let mut mask = Vec::new();
+        for (field, api_field) in &changes {
+            let value = match *field {
+                "name" => serde_json::json!(place.name),
+                "description" => serde_json::json!(place.description),
+                _ => serde_json::json!(place.max_players),
+            };
+            patch.insert(api_field.to_string(), value);
+            mask.push(*api_field);
+        }
+        let response = client.update_place(universe_id, place.place_id, &serde_json::Value::Object(patch), &mask).await?;
+
+        // Record what Roblox reports back, keeping the stored value for anything it omits
+        let entry = state.places.entry(place.place_id).or_default();
+        if fields.contains(&"name") {
+            entry.name = response["displayName"].as_str().map(String::from).or(stored.name);
+        }
+        if fields.contains(&"description") {
+            entry.description = response["description"].as_str().map(String::from).or(stored.description);
+        }
+        if fields.contains(&"max_players") {
+            entry.max_players = response["serverSize"].as_u64().map(|n| n as u32).or(stored.max_players);
+        }
+        backend.save(state).await?;
+        info!("  [UPDATED] Place {} - updated: {}", place.place_id, fields.join(", "));
+    }
+    Ok(())
+}
+
+/// Settings of `place` that are set and differ from the stored state, as
+/// (config field, Open Cloud field) pairs
+fn place_changes(stored: &PlaceState, place: &PlaceConfig) -> Vec<(&'static str, &'static str)> {
+    let mut changes = Vec::new();
+    if place.name.is_some() && place.name != stored.name {
+        changes.push(("name", "displayName"));
+    }
+    if place.description.is_some() && place.description != stored.description {
+        changes.push(("description", "description"));
+    }
+    if place.max_players.is_some() && place.max_players != stored.max_players {
+        changes.push(("max_players", "serverSize"));
+    }
+    changes
+}
+
+async fn sync_game_passes(universe_id: u64, config: &RblxSyncConfig, project: &Project, state: &mut SyncState, backend: &mut dyn StateBackend, client: &RobloxClient, dry_run: bool) -> Result<()> {
     info!("Syncing Game Passes...");
     
     let mut created_count = 0;
@@ -269,7 +491,7 @@ async fn sync_game_passes(universe_id: u64, config: &RblxSyncConfig, state: &mut
 
         // Handle Icon - calculate hash and check for changes
         if let Some(icon_path_str) = &pass.icon {
-            let icon_path = Path::new(&config.assets_dir).join(icon_path_str);
+            let icon_path = project.asset_path(&config.assets_dir, icon_path_str);
             let current_hash = calculate_file_hash(&icon_path).await?;
             let stored_hash = state_entry.and_then(|s| s.icon_hash.as_ref());
             
@@ -351,7 +573,7 @@ async fn sync_game_passes(universe_id: u64, config: &RblxSyncConfig, state: &mut
             // Read image file if icon changed
             let image_data = if icon_changed {
                 if let Some(icon_path_str) = &pass.icon {
-                    let icon_path = Path::new(&config.assets_dir).join(icon_path_str);
+                    let icon_path = project.asset_path(&config.assets_dir, icon_path_str);
                     if icon_path.exists() {
                         let data = tokio::fs::read(&icon_path).await?;
                         let filename = icon_path.file_name().unwrap_or_default().to_string_lossy().to_string();
@@ -376,7 +598,8 @@ async fn sync_game_passes(universe_id: u64, config: &RblxSyncConfig, state: &mut
             skipped_count += 1;
         }
 
-        // Update State after successful sync
+        // Update State after successful sync, persisting immediately after a
+        // create/update so a later failure cannot lose track of this resource
         if !dry_run && id != 0 {
             state.update_game_pass(
                 id,
@@ -387,6 +610,14 @@ async fn sync_game_passes(universe_id: u64, config: &RblxSyncConfig, state: &mut
                 icon_hash.clone(), 
                 asset_id
             );
+            let remote_item = existing.data.iter().find(|item| resource_id(item, ResourceKind::GamePass) == Some(id));
+            let (created, updated) = remote_item.map(remote_timestamps).unwrap_or_default();
+            if state.record_sync(ResourceKind::GamePass, id, created, updated, is_new || has_changes) {
+                warn!("  [DRIFT] Game Pass '{}' (ID: {}) was changed on Roblox since the last sync", pass.name, id);
+            }
+            if is_new || has_changes {
+                backend.save(state).await?;
+            }
         }
     }
     
@@ -395,7 +626,7 @@ async fn sync_game_passes(universe_id: u64, config: &RblxSyncConfig, state: &mut
     Ok(())
 }
 
-async fn sync_developer_products(universe_id: u64, config: &RblxSyncConfig, state: &mut SyncState, client: &RobloxClient, dry_run: bool) -> Result<()> {
+async fn sync_developer_products(universe_id: u64, config: &RblxSyncConfig, project: &Project, state: &mut SyncState, backend: &mut dyn StateBackend, client: &RobloxClient, dry_run: bool) -> Result<()> {
     info!("Syncing Developer Products...");
     
     let mut created_count = 0;
@@ -452,7 +683,7 @@ async fn sync_developer_products(universe_id: u64, config: &RblxSyncConfig, stat
         }
 
         if let Some(icon_path_str) = &prod.icon {
-            let icon_path = Path::new(&config.assets_dir).join(icon_path_str);
+            let icon_path = project.asset_path(&config.assets_dir, icon_path_str);
             let current_hash = calculate_file_hash(&icon_path).await?;
             let stored_hash = state_entry.and_then(|s| s.icon_hash.as_ref());
             
@@ -530,7 +761,7 @@ async fn sync_developer_products(universe_id: u64, config: &RblxSyncConfig, stat
             // Read image file if icon changed
             let image_data = if icon_changed {
                 if let Some(icon_path_str) = &prod.icon {
-                    let icon_path = Path::new(&config.assets_dir).join(icon_path_str);
+                    let icon_path = project.asset_path(&config.assets_dir, icon_path_str);
                     if icon_path.exists() {
                         let data = tokio::fs::read(&icon_path).await?;
                         let filename = icon_path.file_name().unwrap_or_default().to_string_lossy().to_string();
@@ -555,7 +786,8 @@ async fn sync_developer_products(universe_id: u64, config: &RblxSyncConfig, stat
             skipped_count += 1;
         }
 
-        // Update State after successful sync
+        // Update State after successful sync, persisting immediately after a
+        // create/update so a later failure cannot lose track of this resource
         if !dry_run && id != 0 {
             state.update_developer_product(
                 id,
@@ -565,6 +797,14 @@ async fn sync_developer_products(universe_id: u64, config: &RblxSyncConfig, stat
                 icon_hash, 
                 asset_id
             );
+            let remote_item = existing.data.iter().find(|item| resource_id(item, ResourceKind::DeveloperProduct) == Some(id));
+            let (created, updated) = remote_item.map(remote_timestamps).unwrap_or_default();
+            if state.record_sync(ResourceKind::DeveloperProduct, id, created, updated, is_new || has_changes) {
+                warn!("  [DRIFT] Developer Product '{}' (ID: {}) was changed on Roblox since the last sync", prod.name, id);
+            }
+            if is_new || has_changes {
+                backend.save(state).await?;
+            }
         }
     }
     
@@ -573,7 +813,7 @@ async fn sync_developer_products(universe_id: u64, config: &RblxSyncConfig, stat
     Ok(())
 }
 
-async fn sync_badges(universe_id: u64, config: &RblxSyncConfig, state: &mut SyncState, client: &RobloxClient, dry_run: bool) -> Result<()> {
+async fn sync_badges(universe_id: u64, config: &RblxSyncConfig, project: &Project, state: &mut SyncState, backend: &mut dyn StateBackend, client: &RobloxClient, dry_run: bool) -> Result<()> {
     info!("Syncing Badges...");
     
     let mut created_count = 0;
@@ -593,7 +833,7 @@ async fn sync_badges(universe_id: u64, config: &RblxSyncConfig, state: &mut Sync
     };
 
     let mut remote_map: HashMap<String, (String, u64)> = HashMap::new();
-    for item in existing.data {
+    for item in &existing.data {
         if let (Some(name), Some(id)) = (item["name"].as_str(), item["id"].as_u64()) {
             remote_map.insert(name.to_lowercase(), (name.to_string(), id));
         }
@@ -620,7 +860,7 @@ async fn sync_badges(universe_id: u64, config: &RblxSyncConfig, state: &mut Sync
         
         // Prepare icon data if provided
         let icon_data = if let Some(icon_path_str) = &badge.icon {
-            let icon_path = Path::new(&config.assets_dir).join(icon_path_str);
+            let icon_path = project.asset_path(&config.assets_dir, icon_path_str);
             if icon_path.exists() {
                 let data = tokio::fs::read(&icon_path).await?;
                 let filename = icon_path.file_name().unwrap_or_default().to_string_lossy().to_string();
@@ -744,7 +984,8 @@ async fn sync_badges(universe_id: u64, config: &RblxSyncConfig, state: &mut Sync
             skipped_count += 1;
         }
 
-        // Update State after successful sync
+        // Update State after successful sync, persisting immediately after a
+        // create/update so a later failure cannot lose track of this resource
         if !dry_run && id != 0 {
             state.update_badge(
                 id,
@@ -754,6 +995,14 @@ async fn sync_badges(universe_id: u64, config: &RblxSyncConfig, state: &mut Sync
                 icon_hash.clone(), 
                 None
             );
+            let remote_item = existing.data.iter().find(|item| resource_id(item, ResourceKind::Badge) == Some(id));
+            let (created, updated) = remote_item.map(remote_timestamps).unwrap_or_default();
+            if state.record_sync(ResourceKind::Badge, id, created, updated, is_new || has_changes) {
+                warn!("  [DRIFT] Badge '{}' (ID: {}) was changed on Roblox since the last sync", badge.name, id);
+            }
+            if is_new || has_changes {
+                backend.save(state).await?;
+            }
         }
     }
     
@@ -762,79 +1011,712 @@ async fn sync_badges(universe_id: u64, config: &RblxSyncConfig, state: &mut Sync
     Ok(())
 }
 
-/// Check for duplicate names (case-insensitive) in a list
-fn check_for_duplicates(names: &[&str], resource_type: &str) -> Result<()> {
-    let mut seen: HashSet<String> = HashSet::new();
-    let mut duplicates: Vec<String> = Vec::new();
-    
-    for name in names {
-        let lower = name.to_lowercase();
-        if seen.contains(&lower) {
-            duplicates.push((*name).to_string());
-        } else {
-            seen.insert(lower);
-        }
-    }
-    
-    if !duplicates.is_empty() {
-        return Err(anyhow!(
-            "Duplicate {} names found (names must be unique, case-insensitive): {:?}",
-            resource_type,
-            duplicates
-        ));
-    }
-    
-    Ok(())
-}
+async fn sync_subscriptions(universe_id: u64, config: &RblxSyncConfig, project: &Project, state: &mut SyncState, backend: &mut dyn StateBackend, client: &RobloxClient, dry_run: bool) -> Result<()> {
+    info!("Syncing Subscriptions...");
 
-/// Calculate SHA-256 hash of a file
-async fn calculate_file_hash(path: &Path) -> Result<String> {
-    if !path.exists() {
-        return Err(anyhow!("File not found: {:?}", path));
-    }
-    let content = tokio::fs::read(path).await?;
-    let mut hasher = Sha256::new();
-    hasher.update(&content);
-    Ok(format!("{:x}", hasher.finalize()))
-}
+    let mut created_count = 0;
+    let mut updated_count = 0;
+    let mut skipped_count = 0;
 
-async fn ensure_icon(client: &RobloxClient, path: &Path, state: Option<&ResourceState>, creator: &crate::config::CreatorConfig) -> Result<(u64, String)> {
-    if !path.exists() {
-        return Err(anyhow!("Icon file not found: {:?}", path));
+    let existing = match list_all(|token| client.list_subscriptions(universe_id, token)).await {
+        Ok(items) => items,
+        Err(e) if dry_run => {
+            warn!("Dry Run: Failed to list subscriptions: {}", e);
+            Vec::new()
+        }
+        Err(e) => return Err(e),
+    };
+
+    let mut remote_map: HashMap<String, u64> = HashMap::new();
+    for item in &existing {
+        log::debug!("Subscription item from API: {}", item);
+        if let (Some(name), Some(id)) = (item["name"].as_str(), resource_id(item, ResourceKind::Subscription)) {
+            remote_map.insert(name.to_lowercase(), id);
+        }
     }
 
-    // Calculate Hash
-    let content = tokio::fs::read(path).await?;
-    let mut hasher = Sha256::new();
-    hasher.update(&content);
-    let hash = format!("{:x}", hasher.finalize());
+    for subscription in &config.subscriptions {
+        let state_lookup = state.find_subscription_by_name(&subscription.name);
+        let state_entry = state_lookup.map(|(_, s)| s);
+        let period = subscription.period.as_str();
+        let mut asset_id = None;
+        let mut icon_hash = None;
+        let mut icon_changed = false;
+        let mut changes: Vec<&str> = Vec::new();
 
-    // Check State
-    if let Some(s) = state {
-        if let (Some(sh), Some(sid)) = (&s.icon_hash, s.icon_asset_id) {
-            if sh == &hash {
-                return Ok((sid, hash));
+        if let Some(entry) = state_entry {
+            if entry.name != subscription.name {
+                changes.push("name");
+            }
+            if entry.description.as_ref() != subscription.description.as_ref() {
+                changes.push("description");
+            }
+            if entry.price_tier.as_deref() != Some(subscription.price_tier.as_str()) {
+                changes.push("price_tier");
+            }
+            if entry.period.as_deref() != Some(period) {
+                changes.push("period");
+            }
+            if entry.is_enabled != subscription.is_active {
+                changes.push("is_active");
             }
         }
-    }
 
-    // Upload
-    info!("Uploading icon: {:?}", path);
-    let name = path.file_stem().unwrap_or_default().to_string_lossy();
-    let asset_id_str = client.upload_asset(path, &name, creator).await?;
-    let asset_id = asset_id_str.parse::<u64>()?;
-    
-    Ok((asset_id, hash))
-}
+        if let Some(icon_path_str) = &subscription.icon {
+            let icon_path = project.asset_path(&config.assets_dir, icon_path_str);
+            let current_hash = calculate_file_hash(&icon_path).await?;
+            let stored_hash = state_entry.and_then(|s| s.icon_hash.as_ref());
 
-pub async fn export(config: RblxSyncConfig, client: RobloxClient, output: Option<String>, format_lua: bool) -> Result<()> {
-    let universe_id = config.universe.id;
+            if stored_hash == Some(&current_hash) && state_entry.and_then(|s| s.icon_asset_id).is_some() {
+                asset_id = state_entry.and_then(|s| s.icon_asset_id);
+                icon_hash = Some(current_hash);
+            } else if dry_run {
+                asset_id = Some(0);
+                icon_hash = Some(current_hash);
+                icon_changed = true;
+                changes.push("icon");
+            } else {
+                let creator = config.creator.as_ref().ok_or_else(|| anyhow!("Creator configuration is required for asset uploads"))?;
+                let (aid, hash) = ensure_icon(client, &icon_path, state_entry, creator).await?;
+                asset_id = Some(aid);
+                icon_hash = Some(hash);
+                icon_changed = true;
+                changes.push("icon");
+            }
+        }
 
-    info!("Exporting universe {}...", universe_id);
-    // Fetch all data
-    let passes = client.list_game_passes(universe_id, None).await?;
-    let products = client.list_developer_products(universe_id, None).await?;
-    let badges = client.list_badges(universe_id, None).await?;
+        // Determine ID (State -> Remote -> Create) - case-insensitive matching
+        let state_id = state_lookup.map(|(id, _)| id);
+        let remote_id = remote_map.get(&subscription.name.to_lowercase()).copied();
+        let is_new = state_id.is_none() && remote_id.is_none();
+        let has_changes = !changes.is_empty();
+
+        let id = if let Some(id) = state_id.or(remote_id) {
+            id
+        } else if dry_run {
+            info!("  [CREATE] Subscription '{}' - would create with: name, description, price_tier, period{}",
+                subscription.name,
+                if subscription.icon.is_some() { ", icon" } else { "" });
+            created_count += 1;
+            0
+        } else {
+            let mut body = serde_json::json!({
+                "name": subscription.name,
+                "description": subscription.description.clone().unwrap_or_default(),
+                "priceTier": subscription.price_tier,
+                "period": subscription.period.api_value(),
+            });
+            if let Some(active) = subscription.is_active {
+                body["isActive"] = active.into();
+            }
+            if let Some(aid) = asset_id {
+                body["iconAssetId"] = aid.into();
+            }
+
+            let resp = client.create_subscription(universe_id, &body).await?;
+            let new_id = resource_id(&resp, ResourceKind::Subscription).ok_or(anyhow!("Created subscription has no ID"))?;
+            info!("  [CREATED] Subscription '{}' (ID: EXP-{}) - created with: name, description, price_tier, period{}",
+                subscription.name, new_id,
+                if subscription.icon.is_some() { ", icon" } else { "" });
+            created_count += 1;
+            new_id
+        };
+
+        if is_new {
+            // Already created above
+        } else if !has_changes {
+            info!("  [SKIP] Subscription '{}' (ID: EXP-{}) - no changes detected", subscription.name, id);
+            skipped_count += 1;
+        } else if dry_run {
+            info!("  [UPDATE] Subscription '{}' (ID: EXP-{}) - would update: {}",
+                subscription.name, id, changes.join(", "));
+            updated_count += 1;
+        } else {
+            let mut patch = serde_json::Map::new();
+            patch.insert("name".to_string(), subscription.name.clone().into());
+            if let Some(d) = &subscription.description { patch.insert("description".to_string(), d.clone().into()); }
+            patch.insert("priceTier".to_string(), subscription.price_tier.clone().into());
+            patch.insert("period".to_string(), subscription.period.api_value().into());
+            if let Some(a) = subscription.is_active { patch.insert("isActive".to_string(), a.into()); }
+
+            let image_data = match &subscription.icon {
+                Some(icon_path_str) if icon_changed => {
+                    let icon_path = project.asset_path(&config.assets_dir, icon_path_str);
+                    let data = tokio::fs::read(&icon_path).await?;
+                    let filename = icon_path.file_name().unwrap_or_default().to_string_lossy().to_string();
+                    Some((data, filename))
+                }
+                _ => None,
+            };
+
+            client.update_subscription_with_icon(universe_id, id, &serde_json::Value::Object(patch), image_data).await?;
+            info!("  [UPDATED] Subscription '{}' (ID: EXP-{}) - updated: {}",
+                subscription.name, id, changes.join(", "));
+            updated_count += 1;
+        }
+
+        // Update State after successful sync, persisting immediately after a
+        // create/update so a later failure cannot lose track of this resource
+        if !dry_run && id != 0 {
+            state.update_subscription(
+                id,
+                subscription.name.clone(),
+                subscription.description.clone(),
+                subscription.price_tier.clone(),
+                period.to_string(),
+                subscription.is_active,
+                icon_hash.clone(),
+                asset_id
+            );
+            let remote_item = existing.iter().find(|item| resource_id(item, ResourceKind::Subscription) == Some(id));
+            let (created, updated) = remote_item.map(remote_timestamps).unwrap_or_default();
+            if state.record_sync(ResourceKind::Subscription, id, created, updated, is_new || has_changes) {
+                warn!("  [DRIFT] Subscription '{}' (ID: EXP-{}) was changed on Roblox since the last sync", subscription.name, id);
+            }
+            if is_new || has_changes {
+                backend.save(state).await?;
+            }
+        }
+    }
+
+    info!("Subscriptions Summary: {} created, {} updated, {} skipped (unchanged)",
+        created_count, updated_count, skipped_count);
+    Ok(())
+}
+
+/// Write the configured DataStore entries whose value differs from the last
+/// value rblxsync wrote. Entries rblxsync has not written before are read first,
+/// so values that are already in place are only recorded.
+async fn sync_data_stores(universe_id: u64, config: &RblxSyncConfig, project: &Project, state: &mut SyncState, backend: &mut dyn StateBackend, client: &RobloxClient, dry_run: bool) -> Result<()> {
+    info!("Syncing DataStores...");
+
+    let mut created_count = 0;
+    let mut updated_count = 0;
+    let mut skipped_count = 0;
+
+    for store in &config.data_stores {
+        for entry in &store.entries {
+            let label = format!("{}/{}/{}", store.name, store.scope, entry.key);
+            let value = entry.load_value(project)?;
+            let hash = DataStoreEntryState::hash(&value);
+
+            let stored = state.data_store_entry(&store.name, &store.scope, &entry.key);
+            if stored.is_some_and(|s| s.hash == hash) {
+                info!("  [SKIP] DataStore entry '{}' - no changes detected", label);
+                skipped_count += 1;
+                continue;
+            }
+
+            // Untracked entries may already hold the value (seeded by hand or by another repo)
+            let remote = if stored.is_none() {
+                match client.get_data_store_entry(universe_id, &store.name, &store.scope, &entry.key).await {
+                    Ok(remote) => remote,
+                    Err(e) if dry_run => {
+                        warn!("Dry Run: Failed to read DataStore entry '{}': {}", label, e);
+                        None
+                    }
+                    Err(e) => return Err(e),
+                }
+            } else {
+                None
+            };
+            let content_md5 = data_store_content_md5(&value)?;
+            if let Some(remote) = remote.as_ref().filter(|r| r.content_md5.as_deref() == Some(content_md5.as_str())) {
+                info!("  [SKIP] DataStore entry '{}' - already up to date", label);
+                skipped_count += 1;
+                if !dry_run {
+                    state.set_data_store_entry(&store.name, &store.scope, &entry.key, DataStoreEntryState {
+                        hash,
+                        version: remote.version.clone(),
+                        content_md5: Some(content_md5),
+                        last_synced_at: None,
+                    });
+                    backend.save(state).await?;
+                }
+                continue;
+            }
+
+            let is_new = stored.is_none() && remote.is_none();
+            if dry_run {
+                if is_new {
+                    info!("  [CREATE] DataStore entry '{}' - would create", label);
+                    created_count += 1;
+                } else {
+                    info!("  [UPDATE] DataStore entry '{}' - would update: value", label);
+                    updated_count += 1;
+                }
+                continue;
+            }
+
+            let result = client.set_data_store_entry(universe_id, &store.name, &store.scope, &entry.key, &value, None, None).await?;
+            state.set_data_store_entry(&store.name, &store.scope, &entry.key, DataStoreEntryState {
+                hash,
+                version: result["version"].as_str().map(String::from),
+                content_md5: Some(content_md5),
+                last_synced_at: Some(chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true)),
+            });
+            backend.save(state).await?;
+            if is_new {
+                info!("  [CREATED] DataStore entry '{}'", label);
+                created_count += 1;
+            } else {
+                info!("  [UPDATED] DataStore entry '{}' - updated: value", label);
+                updated_count += 1;
+            }
+        }
+    }
+
+    info!("DataStores Summary: {} created, {} updated, {} skipped (unchanged)",
+        created_count, updated_count, skipped_count);
+    Ok(())
+}
+
+async fn sync_user_restrictions(universe_id: u64, config: &RblxSyncConfig, state: &mut SyncState, backend: &mut dyn StateBackend, client: &RobloxClient, dry_run: bool) -> Result<()> {
+    info!("Syncing User Restrictions...");
+
+    let mut desired = BTreeMap::new();
+    for restriction in &config.user_restrictions {
+        let restriction = restriction.to_state()?;
+        desired.insert(UserRestrictionState::key(restriction.user_id, restriction.place_id), restriction);
+    }
+
+    // Active bans on Roblox, for the universe and every place rblxsync bans from
+    let place_ids: BTreeSet<Option<u64>> = std::iter::once(None)
+        .chain(desired.values().chain(state.user_restrictions.values()).filter_map(|r| r.place_id).map(Some))
+        .collect();
+    let mut live = BTreeMap::new();
+    for place_id in place_ids {
+        for item in list_all(|token| client.list_user_restrictions(universe_id, place_id, token)).await? {
+            if let Some(restriction) = active_restriction(&item) {
+                live.insert(UserRestrictionState::key(restriction.user_id, restriction.place_id), restriction);
+            }
+        }
+    }
+
+    let mut created_count = 0;
+    let mut updated_count = 0;
+    let mut lifted_count = 0;
+    let mut skipped_count = 0;
+
+    for (key, restriction) in &desired {
+        let label = restriction_label(restriction);
+        let current = live.get(key);
+        if let Some(current) = current.filter(|c| c.same_terms(restriction)) {
+            info!("  [SKIP] {} - no changes detected", label);
+            skipped_count += 1;
+            if !dry_run && state.user_restrictions.get(key) != Some(current) {
+                state.user_restrictions.insert(key.clone(), current.clone());
+                backend.save(state).await?;
+            }
+            continue;
+        }
+        // A ban that ran out or was lifted by hand stays lifted until its entry changes
+        if current.is_none() && state.user_restrictions.get(key).is_some_and(|s| s.same_terms(restriction)) {
+            info!("  [SKIP] {} - no longer active on Roblox (expired or lifted); change the entry to ban again", label);
+            skipped_count += 1;
+            continue;
+        }
+
+        let changes = current.map(|c| restriction_changes(c, restriction)).unwrap_or_default();
+        if dry_run {
+            match current {
+                None => {
+                    info!("  [CREATE] {} - would ban", label);
+                    created_count += 1;
+                }
+                Some(_) => {
+                    info!("  [UPDATE] {} - would update: {}", label, changes.join(", "));
+                    updated_count += 1;
+                }
+            }
+            continue;
+        }
+
+        let response = client.update_user_restriction(universe_id, restriction.place_id, restriction.user_id, &restriction_body(restriction)).await?;
+        let start_time = response["gameJoinRestriction"]["startTime"].as_str().map(String::from);
+        state.user_restrictions.insert(key.clone(), UserRestrictionState { start_time, ..restriction.clone() });
+        backend.save(state).await?;
+        match current {
+            None => {
+                info!("  [CREATED] {}", label);
+                created_count += 1;
+            }
+            Some(_) => {
+                info!("  [UPDATED] {} - updated: {}", label, changes.join(", "));
+                updated_count += 1;
+            }
+        }
+    }
+
+    // Bans rblxsync applied whose entries were removed from the config; bans
+    // applied by other means are never lifted
+    let removed: Vec<String> = state.user_restrictions.keys().filter(|k| !desired.contains_key(*k)).cloned().collect();
+    for key in removed {
+        let restriction = state.user_restrictions[&key].clone();
+        let label = restriction_label(&restriction);
+        match live.get(&key) {
+            None => {
+                info!("  [SKIP] {} - removed from config and no longer active", label);
+                skipped_count += 1;
+                if !dry_run {
+                    state.user_restrictions.remove(&key);
+                    backend.save(state).await?;
+                }
+                continue;
+            }
+            // Replaced on Roblox since rblxsync applied it, so the active ban is not ours to lift
+            Some(current) if !current.same_terms(&restriction) => {
+                warn!("  [SKIP] {} - removed from config, but the active ban was changed outside rblxsync; leaving it in place", label);
+                skipped_count += 1;
+                if !dry_run {
+                    state.user_restrictions.remove(&key);
+                    backend.save(state).await?;
+                }
+                continue;
+            }
+            Some(_) => {}
+        }
+        if dry_run {
+            info!("  [LIFT] {} - would lift (removed from config)", label);
+            lifted_count += 1;
+            continue;
+        }
+        client.update_user_restriction(universe_id, restriction.place_id, restriction.user_id, &serde_json::json!({ "active": false })).await?;
+        state.user_restrictions.remove(&key);
+        backend.save(state).await?;
+        info!("  [LIFTED] {}", label);
+        lifted_count += 1;
+    }
+
+    info!("User Restrictions Summary: {} created, {} updated, {} lifted, {} skipped (unchanged)",
+        created_count, updated_count, lifted_count, skipped_count);
+    Ok(())
+}
+
+/// The ban described by a `UserRestriction` resource, if it is active
+fn active_restriction(item: &serde_json::Value) -> Option<UserRestrictionState> {
+    let restriction = &item["gameJoinRestriction"];
+    if !restriction["active"].as_bool().unwrap_or(false) {
+        return None;
+    }
+    // Named `universes/<id>[/places/<id>]/user-restrictions/<user id>`
+    let path = item["path"].as_str()?;
+    let user_id = path.rsplit('/').next()?.parse().ok()?;
+    let place_id = path.split("/places/").nth(1)
+        .and_then(|rest| rest.split('/').next())
+        .and_then(|id| id.parse().ok());
+    let text = |field: &str| restriction[field].as_str().filter(|s| !s.is_empty()).map(String::from);
+    Some(UserRestrictionState {
+        user_id,
+        place_id,
+        duration: text("duration"),
+        display_reason: text("displayReason"),
+        private_reason: text("privateReason"),
+        exclude_alt_accounts: restriction["excludeAltAccounts"].as_bool().unwrap_or(false),
+        start_time: text("startTime"),
+    })
+}
+
+/// Fields of an active ban that differ from the desired one
+fn restriction_changes(current: &UserRestrictionState, desired: &UserRestrictionState) -> Vec<&'static str> {
+    let mut changes = Vec::new();
+    if current.duration != desired.duration { changes.push("duration"); }
+    if current.display_reason != desired.display_reason { changes.push("display_reason"); }
+    if current.private_reason != desired.private_reason { changes.push("private_reason"); }
+    if current.exclude_alt_accounts != desired.exclude_alt_accounts { changes.push("exclude_alt_accounts"); }
+    changes
+}
+
+fn restriction_body(restriction: &UserRestrictionState) -> serde_json::Value {
+    let mut body = serde_json::json!({
+        "active": true,
+        "excludeAltAccounts": restriction.exclude_alt_accounts,
+    });
+    if let Some(duration) = &restriction.duration {
+        body["duration"] = serde_json::json!(duration);
+    }
+    if let Some(reason) = &restriction.display_reason {
+        body["displayReason"] = serde_json::json!(reason);
+    }
+    if let Some(reason) = &restriction.private_reason {
+        body["privateReason"] = serde_json::json!(reason);
+    }
+    body
+}
+
+fn restriction_label(restriction: &UserRestrictionState) -> String {
+    match restriction.place_id {
+        Some(place_id) => format!("Ban of user {} from place {}", restriction.user_id, place_id),
+        None => format!("Ban of user {}", restriction.user_id),
+    }
+}
+
+/// Fields of `desired` that are set and differ from the stored universe state
+fn universe_changes(stored_state: Option<&UniverseState>, desired_state: &UniverseState) -> Vec<&'static str> {
+    let mut changes: Vec<&str> = Vec::new();
+    
+    if stored_state.map(|s| &s.name) != Some(&desired_state.name) && desired_state.name.is_some() {
+        changes.push("name");
+    }
+    if stored_state.map(|s| &s.description) != Some(&desired_state.description) && desired_state.description.is_some() {
+        changes.push("description");
+    }
+    if stored_state.map(|s| &s.genre) != Some(&desired_state.genre) && desired_state.genre.is_some() {
+        changes.push("genre");
+    }
+    if stored_state.map(|s| &s.playable_devices) != Some(&desired_state.playable_devices) && desired_state.playable_devices.is_some() {
+        changes.push("playable_devices");
+    }
+    if stored_state.map(|s| &s.max_players) != Some(&desired_state.max_players) && desired_state.max_players.is_some() {
+        changes.push("max_players");
+    }
+    if stored_state.map(|s| &s.private_server_cost) != Some(&desired_state.private_server_cost) && desired_state.private_server_cost.is_some() {
+        changes.push("private_server_cost");
+    }
+    
+    changes
+}
+
+/// Apply the changed universe fields and record the universe state as confirmed
+/// by Roblox. Settings live in the universe configuration, except `max_players`,
+/// which is the server size of the universe's start place.
+async fn apply_universe_changes(universe_id: u64, state: &mut SyncState, desired: &UniverseState, changes: &[&str], cookie_client: &RobloxCookieClient) -> Result<()> {
+    let body = universe_patch(desired, changes);
+    let mut confirmed = state.universe.clone().unwrap_or_default();
+
+    if body.as_object().is_some_and(|b| !b.is_empty()) {
+        info!("  Request URL: https://develop.roblox.com/v2/universes/{}/configuration", universe_id);
+        info!("  Request Body: {}", serde_json::to_string_pretty(&body).unwrap_or_default());
+        let response = cookie_client.update_universe_configuration(universe_id, &body).await?;
+        
+        // Output raw response
+        info!("  Universe API Response: {}", serde_json::to_string_pretty(&response).unwrap_or_else(|_| response.to_string()));
+        confirmed = confirmed_universe(confirmed, desired, changes, &response);
+    }
+
+    if changes.contains(&"max_players") {
+        if let Some(max_players) = desired.max_players {
+            let root_place_id = root_place_id(universe_id, cookie_client).await?;
+            if let Some(count) = set_place_max_players(root_place_id, max_players, cookie_client).await? {
+                confirmed.max_players = Some(count);
+                // Keep the place's own record current, so it is not compared against a stale size
+                if let Some(place) = state.places.get_mut(&root_place_id) {
+                    place.max_players = Some(count);
+                }
+            }
+        }
+    }
+
+    state.universe = Some(confirmed);
+    Ok(())
+}
+
+async fn root_place_id(universe_id: u64, cookie_client: &RobloxCookieClient) -> Result<u64> {
+    let details = cookie_client.get_universe_details(universe_id).await?;
+    details["rootPlaceId"].as_u64()
+        .ok_or_else(|| anyhow!("Universe {} has no start place to apply max_players to", universe_id))
+}
+
+/// Set a place's server size, returning the size Roblox confirmed (if it reported one)
+async fn set_place_max_players(place_id: u64, max_players: u32, cookie_client: &RobloxCookieClient) -> Result<Option<u32>> {
+    let response = cookie_client.update_place_configuration(place_id, &serde_json::json!({ "maxPlayerCount": max_players })).await?;
+    let confirmed = response["maxPlayerCount"].as_u64().map(|n| n as u32);
+    match confirmed {
+        None => warn!("  Roblox did not confirm max_players for place {}; it will be retried on the next run", place_id),
+        Some(count) if count != max_players => warn!("  Roblox set max_players for place {} to {} instead of {}", place_id, count, max_players),
+        _ => {}
+    }
+    Ok(confirmed)
+}
+
+/// Update `confirmed` with the changed fields echoed back in a universe configuration
+/// response. Fields missing from the response keep their previously recorded value,
+/// so they are sent again on the next run.
+fn confirmed_universe(mut confirmed: UniverseState, desired: &UniverseState, changes: &[&str], response: &serde_json::Value) -> UniverseState {
+    // Keep the config's spelling when Roblox only normalized the case
+    let text = |key: &str, wanted: &Option<String>| -> Option<String> {
+        let value = response[key].as_str()?;
+        match wanted {
+            Some(wanted) if wanted.eq_ignore_ascii_case(value) => Some(wanted.clone()),
+            _ => Some(value.to_string()),
+        }
+    };
+
+    for field in changes {
+        let value_confirmed = match *field {
+            "name" => text("name", &desired.name).map(|v| confirmed.name = Some(v)).is_some(),
+            "description" => text("description", &desired.description).map(|v| confirmed.description = Some(v)).is_some(),
+            "genre" => text("genre", &desired.genre).map(|v| confirmed.genre = Some(v)).is_some(),
+            "playable_devices" => response["playableDevices"].as_array().map(|devices| {
+                let mut names: Vec<String> = devices.iter().filter_map(|d| match d {
+                    serde_json::Value::Number(n) => match n.as_u64() {
+                        Some(1) => Some("computer".to_string()),
+                        Some(2) => Some("phone".to_string()),
+                        Some(3) => Some("tablet".to_string()),
+                        Some(4) => Some("console".to_string()),
+                        Some(5) => Some("vr".to_string()),
+                        _ => None,
+                    },
+                    serde_json::Value::String(s) => Some(s.to_lowercase()),
+                    _ => None,
+                }).collect();
+                // Order is not significant; keep the config's order when the sets match
+                if let Some(wanted) = &desired.playable_devices {
+                    let mut wanted_sorted: Vec<String> = wanted.iter().map(|d| d.to_lowercase()).collect();
+                    wanted_sorted.sort();
+                    names.sort();
+                    if wanted_sorted == names {
+                        names = wanted.clone();
+                    }
+                }
+                confirmed.playable_devices = Some(names);
+            }).is_some(),
+            "private_server_cost" => response["allowPrivateServers"].as_bool().map(|allowed| {
+                confirmed.private_server_cost = Some(if allowed {
+                    response["privateServerPrice"].as_u64().unwrap_or(0).to_string()
+                } else {
+                    "disabled".to_string()
+                });
+            }).is_some(),
+            // Applied separately through the start place
+            _ => continue,
+        };
+        if !value_confirmed {
+            warn!("  Roblox did not confirm {}; it will be retried on the next run", field);
+        }
+    }
+    confirmed
+}
+
+/// Build the request body for develop.roblox.com/v2/universes/{id}/configuration
+/// containing the changed fields of `desired`
+fn universe_patch(desired: &UniverseState, changes: &[&str]) -> serde_json::Value {
+    let mut body = serde_json::Map::new();
+    
+    // Add fields that are changing
+    if changes.contains(&"name") {
+        if let Some(name) = &desired.name {
+            body.insert("name".to_string(), name.clone().into());
+        }
+    }
+    if changes.contains(&"description") {
+        if let Some(desc) = &desired.description {
+            body.insert("description".to_string(), desc.clone().into());
+        }
+    }
+    if changes.contains(&"genre") {
+        if let Some(genre) = &desired.genre {
+            body.insert("genre".to_string(), genre.clone().into());
+        }
+    }
+    
+    // Map playable devices to numeric array (1=Computer, 2=Phone, 3=Tablet, 4=Console, 5=VR)
+    if changes.contains(&"playable_devices") {
+        if let Some(devices) = &desired.playable_devices {
+            let device_ids: Vec<u8> = devices.iter().filter_map(|d| {
+                match d.to_lowercase().as_str() {
+                    "computer" => Some(1),
+                    "phone" => Some(2),
+                    "tablet" => Some(3),
+                    "console" => Some(4),
+                    "vr" => Some(5),
+                    _ => None,
+                }
+            }).collect();
+            body.insert("playableDevices".to_string(), serde_json::json!(device_ids));
+        }
+    }
+    
+    // Handle private server cost ("disabled", "0" for free, or the price)
+    if changes.contains(&"private_server_cost") {
+        match desired.private_server_cost.as_deref() {
+            Some("disabled") => {
+                body.insert("allowPrivateServers".to_string(), serde_json::json!(false));
+            }
+            Some(price) => {
+                body.insert("allowPrivateServers".to_string(), serde_json::json!(true));
+                body.insert("privateServerPrice".to_string(), serde_json::json!(price.parse::<u32>().unwrap_or(0)));
+            }
+            None => {}
+        }
+    }
+    
+    serde_json::Value::Object(body)
+}
+
+/// Check for duplicate names (case-insensitive) in a list
+fn check_for_duplicates(names: &[&str], resource_type: &str) -> Result<()> {
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut duplicates: Vec<String> = Vec::new();
+    
+    for name in names {
+        let lower = name.to_lowercase();
+        if seen.contains(&lower) {
+            duplicates.push((*name).to_string());
+        } else {
+            seen.insert(lower);
+        }
+    }
+    
+    if !duplicates.is_empty() {
+        return Err(anyhow!(
+            "Duplicate {} names found (names must be unique, case-insensitive): {:?}",
+            resource_type,
+            duplicates
+        ));
+    }
+    
+    Ok(())
+}
+
+/// Calculate SHA-256 hash of a file
+async fn calculate_file_hash(path: &Path) -> Result<String> {
+    if !path.exists() {
+        return Err(anyhow!("File not found: {:?}", path));
+    }
+    let content = tokio::fs::read(path).await?;
+    let mut hasher = Sha256::new();
+    hasher.update(&content);
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+async fn ensure_icon(client: &RobloxClient, path: &Path, state: Option<&ResourceState>, creator: &crate::config::CreatorConfig) -> Result<(u64, String)> {
+    if !path.exists() {
+        return Err(anyhow!("Icon file not found: {:?}", path));
+    }
+
+    // Calculate Hash
+    let content = tokio::fs::read(path).await?;
+    let mut hasher = Sha256::new();
+    hasher.update(&content);
+    let hash = format!("{:x}", hasher.finalize());
+
+    // Check State
+    if let Some(s) = state {
+        if let (Some(sh), Some(sid)) = (&s.icon_hash, s.icon_asset_id) {
+            if sh == &hash {
+                return Ok((sid, hash));
+            }
+        }
+    }
+
+    // Upload
+    info!("Uploading icon: {:?}", path);
+    let name = path.file_stem().unwrap_or_default().to_string_lossy();
+    let asset_id_str = client.upload_asset(path, &name, creator).await?;
+    let asset_id = asset_id_str.parse::<u64>()?;
+    
+    Ok((asset_id, hash))
+}
+
+pub async fn export(config: RblxSyncConfig, client: RobloxClient, output: Option<String>, format_lua: bool) -> Result<()> {
+    let universe_id = config.universe.id;
+
+    info!("Exporting universe {}...", universe_id);
+    // Fetch all data
+    let passes = client.list_game_passes(universe_id, None).await?;
+    let products = client.list_developer_products(universe_id, None).await?;
+    let badges = client.list_badges(universe_id, None).await?;
 
     // Generate output
     // Simple Luau table generation
@@ -878,3 +1760,706 @@ pub async fn export(config: RblxSyncConfig, client: RobloxClient, output: Option
     Ok(())
 }
 
+/// Options for `rblxsync init`
+#[derive(Debug, Default)]
+pub struct InitOptions {
+    pub universe_id: Option<u64>,
+    pub creator_id: Option<String>,
+    pub creator_type: Option<String>,
+    /// Prompt on stdin for values not given on the command line
+    pub interactive: bool,
+    /// Overwrite an existing config file
+    pub force: bool,
+}
+
+const ENV_TEMPLATE: &str = "\
+# Roblox Open Cloud API key (required)
+ROBLOX_API_KEY=
+
+# .ROBLOSECURITY cookie (only needed to update universe settings)
+# ROBLOX_COOKIE=
+";
+
+/// Scaffold a new project: config file, assets directory, `.env` template and
+/// `.gitignore` entries. If a client is given, the config is pre-filled with the
+/// universe name/description and its existing passes, products and badges.
+pub async fn init(options: InitOptions, project: &Project, client: Option<RobloxClient>) -> Result<()> {
+    let config_path = project.config_path();
+    if config_path.exists() && !options.force {
+        return Err(anyhow!("Config file already exists at {:?} (use --force to overwrite)", config_path));
+    }
+
+    let universe_id = match options.universe_id {
+        Some(id) => id,
+        None if options.interactive => prompt("Universe ID")?
+            .parse()
+            .map_err(|_| anyhow!("Universe ID must be a number"))?,
+        None => return Err(anyhow!("--universe is required when not running interactively")),
+    };
+
+    let creator_id = match options.creator_id {
+        Some(id) => Some(id),
+        None if options.interactive => Some(prompt("Creator ID for icon uploads (user or group ID, blank to skip)")?)
+            .filter(|id| !id.is_empty()),
+        None => None,
+    };
+    let creator_type = match options.creator_type {
+        Some(t) => t,
+        None if options.interactive && creator_id.is_some() => Some(prompt("Creator type (user/group) [user]")?)
+            .filter(|t| !t.is_empty())
+            .unwrap_or_else(|| "user".to_string()),
+        None => "user".to_string(),
+    }.to_lowercase();
+    if creator_type != "user" && creator_type != "group" {
+        return Err(anyhow!("Creator type must be \"user\" or \"group\", got \"{}\"", creator_type));
+    }
+
+    let mut config = RblxSyncConfig::new(universe_id);
+    config.creator = creator_id.map(|id| CreatorConfig { id, creator_type });
+    let mut state = SyncState::new(&config.state_scope());
+
+    if let Some(client) = &client {
+        info!("Fetching universe {} from Roblox...", universe_id);
+        let universe = client.get_universe(universe_id).await?;
+        config.universe.name = universe["displayName"].as_str().map(String::from);
+        config.universe.description = universe["description"].as_str().map(String::from);
+        import_resources(universe_id, client, &mut config, &mut state).await?;
+        info!("Found {} game passes, {} developer products, {} badges",
+            config.game_passes.len(), config.developer_products.len(), config.badges.len());
+    }
+
+    std::fs::create_dir_all(project.root())?;
+    config.save(config_path)?;
+    info!("Created {}", config_path.display());
+
+    if !state.game_passes.is_empty() || !state.developer_products.is_empty() || !state.badges.is_empty() {
+        state.save(project.lock_file())?;
+        info!("Created {}", project.lock_file().display());
+    }
+
+    let assets_dir = project.resolve(&config.assets_dir);
+    if !assets_dir.exists() {
+        std::fs::create_dir_all(&assets_dir)?;
+        info!("Created {}", assets_dir.display());
+    }
+
+    let env_path = project.root().join(".env");
+    if env_path.exists() {
+        info!("Keeping existing {}", env_path.display());
+    } else {
+        std::fs::write(&env_path, ENV_TEMPLATE)?;
+        info!("Created {} (fill in ROBLOX_API_KEY)", env_path.display());
+    }
+
+    let gitignore_path = project.root().join(".gitignore");
+    if ensure_gitignore_entries(&gitignore_path, &[".env", "rblxsync-lock.*.lock", ".rblxsync/places/"])? {
+        info!("Updated {}", gitignore_path.display());
+    }
+
+    info!("Project initialized. Add your resources to {} and run `rblxsync run --dry-run`.", config_path.display());
+    Ok(())
+}
+
+/// Ask a question on stdin and return the trimmed answer
+fn prompt(question: &str) -> Result<String> {
+    use std::io::Write;
+
+    print!("{}: ", question);
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(answer.trim().to_string())
+}
+
+/// Append any missing entries to a `.gitignore`, creating it if needed.
+/// Returns whether the file was changed.
+fn ensure_gitignore_entries(path: &Path, entries: &[&str]) -> Result<bool> {
+    let existing = if path.exists() { std::fs::read_to_string(path)? } else { String::new() };
+    let missing: Vec<&str> = entries.iter()
+        .copied()
+        .filter(|entry| !existing.lines().any(|line| line.trim() == *entry))
+        .collect();
+    if missing.is_empty() {
+        return Ok(false);
+    }
+
+    let mut content = existing;
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    content.push_str("# rblxsync\n");
+    for entry in missing {
+        content.push_str(entry);
+        content.push('\n');
+    }
+    std::fs::write(path, content)?;
+    Ok(true)
+}
+
+/// Import existing resources from a universe into a new config file and lock file.
+///
+/// The config format (YAML, TOML or JSON) follows the extension of the project's
+/// config path. Every imported resource is recorded in the lock file so the next
+/// `run` links to it instead of creating a duplicate.
+pub async fn import(universe_id: u64, project: &Project, client: RobloxClient, force: bool) -> Result<()> {
+    let config_path = project.config_path();
+    if config_path.exists() && !force {
+        return Err(anyhow!("Config file already exists at {:?} (use --force to overwrite)", config_path));
+    }
+
+    info!("Importing universe {}...", universe_id);
+    let mut config = RblxSyncConfig::new(universe_id);
+    let mut state = SyncState::new(&config.state_scope());
+    import_resources(universe_id, &client, &mut config, &mut state).await?;
+
+    config.save(config_path)?;
+    state.save(project.lock_file())?;
+    info!("Imported {} game passes, {} developer products, {} badges, {} subscriptions into {}",
+        config.game_passes.len(), config.developer_products.len(), config.badges.len(), config.subscriptions.len(), config_path.display());
+    info!("Lock file written to {}", project.lock_file().display());
+
+    Ok(())
+}
+
+/// Add every remote game pass, developer product, badge and subscription to
+/// `config`, recording their IDs in `state`
+async fn import_resources(universe_id: u64, client: &RobloxClient, config: &mut RblxSyncConfig, state: &mut SyncState) -> Result<()> {
+    let passes = list_all(|cursor| client.list_game_passes(universe_id, cursor)).await?;
+    let products = list_all(|cursor| client.list_developer_products(universe_id, cursor)).await?;
+    let badges = list_all(|cursor| client.list_badges(universe_id, cursor)).await?;
+    let subscriptions = list_all(|cursor| client.list_subscriptions(universe_id, cursor)).await?;
+
+    for item in &passes {
+        let (Some(id), Some(name)) = (resource_id(item, ResourceKind::GamePass), item["name"].as_str()) else { continue };
+        let pass = GamePassConfig {
+            name: name.to_string(),
+            description: item["description"].as_str().map(String::from),
+            price: item["price"].as_u64().map(|p| p as u32),
+            icon: None,
+            is_for_sale: item["isForSale"].as_bool(),
+        };
+        state.update_game_pass(id, pass.name.clone(), pass.description.clone(), pass.price.map(|p| p as u64), pass.is_for_sale, None, None);
+        let (created, updated) = remote_timestamps(item);
+        state.record_sync(ResourceKind::GamePass, id, created, updated, false);
+        config.game_passes.push(pass);
+    }
+
+    for item in &products {
+        let (Some(id), Some(name)) = (resource_id(item, ResourceKind::DeveloperProduct), item["name"].as_str()) else { continue };
+        let product = DeveloperProductConfig {
+            name: name.to_string(),
+            description: item["description"].as_str().map(String::from),
+            price: item["price"].as_u64().unwrap_or(0) as u32,
+            icon: None,
+            is_active: None,
+        };
+        state.update_developer_product(id, product.name.clone(), product.description.clone(), Some(product.price as u64), None, None);
+        let (created, updated) = remote_timestamps(item);
+        state.record_sync(ResourceKind::DeveloperProduct, id, created, updated, false);
+        config.developer_products.push(product);
+    }
+
+    for item in &badges {
+        let (Some(id), Some(name)) = (resource_id(item, ResourceKind::Badge), item["name"].as_str()) else { continue };
+        let badge = BadgeConfig {
+            name: name.to_string(),
+            description: item["description"].as_str().map(String::from),
+            icon: None,
+            is_enabled: item["enabled"].as_bool(),
+        };
+        state.update_badge(id, badge.name.clone(), badge.description.clone(), badge.is_enabled, None, None);
+        let (created, updated) = remote_timestamps(item);
+        state.record_sync(ResourceKind::Badge, id, created, updated, false);
+        config.badges.push(badge);
+    }
+
+    for item in &subscriptions {
+        let id = resource_id(item, ResourceKind::Subscription);
+        let (Some(id), Some(name), Some(price_tier)) = (id, item["name"].as_str(), item["priceTier"].as_str()) else { continue };
+        let subscription = SubscriptionConfig {
+            name: name.to_string(),
+            description: item["description"].as_str().map(String::from),
+            price_tier: price_tier.to_string(),
+            period: SubscriptionPeriod::Monthly,
+            icon: None,
+            is_active: item["isActive"].as_bool(),
+        };
+        state.update_subscription(
+            id,
+            subscription.name.clone(),
+            subscription.description.clone(),
+            subscription.price_tier.clone(),
+            subscription.period.as_str().to_string(),
+            subscription.is_active,
+            None,
+            None
+        );
+        let (created, updated) = remote_timestamps(item);
+        state.record_sync(ResourceKind::Subscription, id, created, updated, false);
+        config.subscriptions.push(subscription);
+    }
+
+    Ok(())
+}
+
+/// Fetch every page of a paginated list endpoint
+pub(crate) async fn list_all<F, Fut>(mut fetch: F) -> Result<Vec<serde_json::Value>>
+where
+    F: FnMut(Option<String>) -> Fut,
+    Fut: std::future::Future<Output = Result<ListResponse<serde_json::Value>>>,
+{
+    let mut items = Vec::new();
+    let mut cursor = None;
+    loop {
+        let page = fetch(cursor).await?;
+        items.extend(page.data);
+        match page.next_page_cursor {
+            Some(next) if !next.is_empty() => cursor = Some(next),
+            _ => break,
+        }
+    }
+    Ok(items)
+}
+
+/// Fields that may hold the Roblox ID in list responses for each resource type
+fn id_keys(kind: ResourceKind) -> &'static [&'static str] {
+    match kind {
+        ResourceKind::GamePass => &["id", "gamePassId"],
+        ResourceKind::DeveloperProduct => &["id", "productId", "developerProductId"],
+        ResourceKind::Badge => &["id"],
+        ResourceKind::Subscription => &["id", "subscriptionId"],
+    }
+}
+
+/// Creation and last-update timestamps reported by Roblox for a listed resource
+fn remote_timestamps(item: &serde_json::Value) -> (Option<String>, Option<String>) {
+    let find = |keys: &[&str]| keys.iter().find_map(|key| item[*key].as_str().map(String::from));
+    (
+        find(&["created", "createdTimestamp", "createdAt", "createTime"]),
+        find(&["updated", "updatedTimestamp", "updatedAt", "updateTime"]),
+    )
+}
+
+/// Read a resource ID from the first of `kind`'s ID keys that matches (numeric or string-encoded)
+fn resource_id(item: &serde_json::Value, kind: ResourceKind) -> Option<u64> {
+    id_keys(kind).iter().find_map(|key| {
+        item[*key].as_u64().or_else(|| {
+            let id = item[*key].as_str()?;
+            // Subscription IDs are strings like `EXP-123`
+            let id = match kind {
+                ResourceKind::Subscription => id.strip_prefix("EXP-").unwrap_or(id),
+                _ => id,
+            };
+            id.parse().ok()
+        })
+    })
+}
+
+// --- State Editing ---
+
+/// Print every resource tracked in the state, grouped by type
+pub fn state_list(state: &SyncState) {
+    if let Some(name) = state.universe.as_ref().and_then(|u| u.name.as_ref()) {
+        println!("universe: {}", name);
+    }
+    for kind in ResourceKind::ALL {
+        let resources = state.resources(kind);
+        if resources.is_empty() {
+            continue;
+        }
+        println!("{}:", kind.section());
+        let mut ids: Vec<&u64> = resources.keys().collect();
+        ids.sort();
+        for id in ids {
+            println!("  {:<16} {}", id, resources[id].name);
+        }
+    }
+    for (store, entries) in &state.data_stores {
+        println!("data_stores/{}:", store);
+        for entry in entries.keys() {
+            println!("  {}", entry);
+        }
+    }
+    if !state.user_restrictions.is_empty() {
+        println!("user_restrictions:");
+        for key in state.user_restrictions.keys() {
+            println!("  {}", key);
+        }
+    }
+}
+
+/// Print the recorded state of one resource, as it appears in the lock file
+pub fn state_show(state: &SyncState, kind: ResourceKind, target: &str) -> Result<()> {
+    let (id, resource) = state.find_resource(kind, target).ok_or_else(|| not_tracked(kind, target))?;
+    let entry = serde_json::json!({ kind.section(): { id.to_string(): resource } });
+    print!("{}", ConfigFormat::Yaml.serialize(&entry)?);
+    Ok(())
+}
+
+/// Stop tracking a resource. It is left untouched on Roblox.
+pub fn state_rm(state: &mut SyncState, kind: ResourceKind, target: &str) -> Result<()> {
+    let (id, name) = state.find_resource(kind, target)
+        .map(|(id, resource)| (id, resource.name.clone()))
+        .ok_or_else(|| not_tracked(kind, target))?;
+    state.resources_mut(kind).remove(&id);
+    info!("Removed {} '{}' ({}) from state. It still exists on Roblox.", kind.label(), name, id);
+    Ok(())
+}
+
+/// Relink a tracked resource to a different existing Roblox ID
+pub async fn state_mv(state: &mut SyncState, universe_id: u64, kind: ResourceKind, target: &str, new_id: u64, client: &RobloxClient) -> Result<()> {
+    let (old_id, name) = state.find_resource(kind, target)
+        .map(|(id, resource)| (id, resource.name.clone()))
+        .ok_or_else(|| not_tracked(kind, target))?;
+    if old_id == new_id {
+        return Err(anyhow!("{} '{}' is already linked to {}", kind.label(), name, new_id));
+    }
+    if let Some(existing) = state.resources(kind).get(&new_id) {
+        return Err(anyhow!("{} {} is already tracked as '{}'; remove it first with `state rm`", kind.label(), new_id, existing.name));
+    }
+
+    let item = find_remote(kind, universe_id, new_id, client).await?;
+    state.resources_mut(kind).remove(&old_id);
+    state.set_resource(kind, new_id, remote_resource_state(name.clone(), &item));
+    info!("Relinked {} '{}' from {} to {}", kind.label(), name, old_id, new_id);
+    Ok(())
+}
+
+/// Start tracking an existing Roblox resource under a config entry name
+pub async fn state_import(state: &mut SyncState, universe_id: u64, kind: ResourceKind, name: &str, id: u64, client: &RobloxClient) -> Result<()> {
+    if let Some(existing) = state.resources(kind).get(&id) {
+        return Err(anyhow!("{} {} is already tracked as '{}'", kind.label(), id, existing.name));
+    }
+    if let Some((existing_id, _)) = state.find_resource(kind, name).filter(|(_, r)| r.name.eq_ignore_ascii_case(name)) {
+        return Err(anyhow!(
+            "{} '{}' is already linked to {}; use `state mv` to relink it", kind.label(), name, existing_id
+        ));
+    }
+
+    let item = find_remote(kind, universe_id, id, client).await?;
+    state.set_resource(kind, id, remote_resource_state(name.to_string(), &item));
+    info!("Imported {} '{}' ({})", kind.label(), name, id);
+    Ok(())
+}
+
+/// Three-way merge of lock files, usable as a git merge driver (`%O %A %B`).
+/// The result is written to `ours`; returns false if there were conflicts, in
+/// which case the conflicting entries keep the `ours` version.
+pub fn state_merge(base: &Path, ours: &Path, theirs: &Path, project: &Project) -> Result<bool> {
+    // Git hands the driver temporary files without an extension
+    let format = ConfigFormat::from_path(ours)
+        .or_else(|_| ConfigFormat::from_path(project.lock_file()))?;
+    let read = |path: &Path| -> Result<LockFile> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read lock file at {:?}: {}", path, e))?;
+        Ok(LockFile::parse(format, &content)?.0)
+    };
+
+    let (merged, conflicts) = crate::state::merge(&read(base)?, &read(ours)?, &read(theirs)?);
+    crate::state::write_atomic(ours, format.serialize(&merged)?.as_bytes())?;
+
+    for conflict in &conflicts {
+        error!("Conflict in {}: both sides changed it; kept our version", conflict);
+    }
+    Ok(conflicts.is_empty())
+}
+
+fn not_tracked(kind: ResourceKind, target: &str) -> anyhow::Error {
+    anyhow!("No {} named or with ID '{}' in state", kind.label(), target)
+}
+
+/// Look up a resource in the universe by ID, failing if it does not exist
+async fn find_remote(kind: ResourceKind, universe_id: u64, id: u64, client: &RobloxClient) -> Result<serde_json::Value> {
+    let items = match kind {
+        ResourceKind::GamePass => list_all(|cursor| client.list_game_passes(universe_id, cursor)).await?,
+        ResourceKind::DeveloperProduct => list_all(|cursor| client.list_developer_products(universe_id, cursor)).await?,
+        ResourceKind::Badge => list_all(|cursor| client.list_badges(universe_id, cursor)).await?,
+        ResourceKind::Subscription => list_all(|token| client.list_subscriptions(universe_id, token)).await?,
+    };
+    items.into_iter()
+        .find(|item| resource_id(item, kind) == Some(id))
+        .ok_or_else(|| anyhow!("{} {} does not exist in universe {}", kind.label(), id, universe_id))
+}
+
+/// Record a remote resource's current values under the given config name, so
+/// the next run only pushes what differs from the config
+fn remote_resource_state(name: String, item: &serde_json::Value) -> ResourceState {
+    let (created_at, remote_updated_at) = remote_timestamps(item);
+    ResourceState {
+        name,
+        description: item["description"].as_str().map(String::from),
+        price: item["price"].as_u64(),
+        price_tier: item["priceTier"].as_str().map(String::from),
+        period: item["period"].as_str().map(|p| p.to_lowercase()),
+        is_for_sale: item["isForSale"].as_bool(),
+        is_enabled: item["enabled"].as_bool().or_else(|| item["isActive"].as_bool()),
+        created_at,
+        remote_updated_at,
+        ..Default::default()
+    }
+}
+
+// --- History ---
+
+/// Print the recorded history snapshots, oldest first
+pub fn history(config: &RblxSyncConfig, project: &Project) -> Result<()> {
+    let snapshots = History::for_project(project, config.state_scope()).list()?;
+    if snapshots.is_empty() {
+        info!("No history recorded yet; a snapshot is taken after every successful run.");
+    }
+    for snapshot in snapshots {
+        let commit = snapshot.git_commit.as_deref().map(|c| &c[..c.len().min(10)]).unwrap_or("-");
+        println!("{:<20} {:<22} {:<10} {}", snapshot.id, snapshot.created_at, commit, snapshot.command);
+    }
+    Ok(())
+}
+
+/// Push the metadata recorded in `snapshot` back to Roblox: universe settings,
+/// and the name/description/price/flags of every resource still tracked.
+/// Icons are left as they are, and resources created after the snapshot are
+/// kept (Roblox does not allow deleting them).
+#[allow(clippy::too_many_arguments)]
+pub async fn rollback(config: &RblxSyncConfig, project: &Project, mut state: SyncState, backend: &mut dyn StateBackend, snapshot: &Snapshot, client: &RobloxClient, cookie_client: Option<&RobloxCookieClient>, dry_run: bool) -> Result<()> {
+    let universe_id = config.universe.id;
+    if let Some(snapshot_universe) = snapshot.state.universe_id.filter(|&id| id != universe_id) {
+        return Err(anyhow!("Snapshot {} belongs to universe {}, not {}", snapshot.id, snapshot_universe, universe_id));
+    }
+    info!("Rolling back to snapshot {} ({}{})", snapshot.id, snapshot.created_at,
+        snapshot.git_commit.as_deref().map(|c| format!(", commit {}", c)).unwrap_or_default());
+
+    if let Some(target) = &snapshot.state.universe {
+        let changes = universe_changes(state.universe.as_ref(), target);
+        if changes.is_empty() {
+            info!("  [SKIP] Universe Settings - no changes");
+        } else if dry_run {
+            info!("  [ROLLBACK] Universe Settings - would revert: {}", changes.join(", "));
+        } else if let Some(cookie_client) = cookie_client {
+            apply_universe_changes(universe_id, &mut state, target, &changes, cookie_client).await?;
+            backend.save(&state).await?;
+            info!("  [ROLLED BACK] Universe Settings - reverted: {}", changes.join(", "));
+        } else {
+            warn!("  Universe Settings differ ({}) but ROBLOX_COOKIE is not set; skipping", changes.join(", "));
+        }
+    }
+
+    for kind in ResourceKind::ALL {
+        let mut ids: Vec<u64> = snapshot.state.resources(kind).keys().copied().collect();
+        ids.sort();
+        for id in ids {
+            let target = &snapshot.state.resources(kind)[&id];
+            let Some(current) = state.resources(kind).get(&id) else {
+                warn!("  {} '{}' (ID: {}) is no longer tracked; skipping", kind.label(), target.name, id);
+                continue;
+            };
+            let changes = resource_changes(current, target);
+            if changes.is_empty() {
+                continue;
+            }
+            if dry_run {
+                info!("  [ROLLBACK] {} '{}' (ID: {}) - would revert: {}", kind.label(), target.name, id, changes.join(", "));
+                continue;
+            }
+
+            let mut patch = serde_json::Map::new();
+            patch.insert("name".to_string(), target.name.clone().into());
+            if let Some(d) = &target.description { patch.insert("description".to_string(), d.clone().into()); }
+            match kind {
+                ResourceKind::GamePass => {
+                    if let Some(p) = target.price { patch.insert("price".to_string(), p.into()); }
+                    if let Some(s) = target.is_for_sale { patch.insert("isForSale".to_string(), s.into()); }
+                    client.update_game_pass(universe_id, id, &serde_json::Value::Object(patch)).await?;
+                }
+                ResourceKind::DeveloperProduct => {
+                    if let Some(p) = target.price { patch.insert("price".to_string(), p.into()); }
+                    client.update_developer_product(universe_id, id, &serde_json::Value::Object(patch)).await?;
+                }
+                ResourceKind::Badge => {
+                    if let Some(e) = target.is_enabled { patch.insert("enabled".to_string(), e.into()); }
+                    client.update_badge(id, &serde_json::Value::Object(patch)).await?;
+                }
+                ResourceKind::Subscription => {
+                    if let Some(t) = &target.price_tier { patch.insert("priceTier".to_string(), t.clone().into()); }
+                    if let Some(a) = target.is_enabled { patch.insert("isActive".to_string(), a.into()); }
+                    client.update_subscription(universe_id, id, &serde_json::Value::Object(patch)).await?;
+                }
+            }
+
+            // Keep the current icon; only the metadata was reverted
+            let reverted = ResourceState {
+                name: target.name.clone(),
+                description: target.description.clone(),
+                price: target.price,
+                price_tier: target.price_tier.clone(),
+                period: target.period.clone(),
+                is_for_sale: target.is_for_sale,
+                is_enabled: target.is_enabled,
+                icon_hash: current.icon_hash.clone(),
+                icon_asset_id: current.icon_asset_id,
+                ..Default::default()
+            };
+            state.set_resource(kind, id, reverted);
+            state.record_sync(kind, id, None, None, true);
+            backend.save(&state).await?;
+            info!("  [ROLLED BACK] {} '{}' (ID: {}) - reverted: {}", kind.label(), target.name, id, changes.join(", "));
+        }
+
+        for (id, resource) in state.resources(kind) {
+            if !snapshot.state.resources(kind).contains_key(id) {
+                info!("  [KEEP] {} '{}' (ID: {}) - created after the snapshot", kind.label(), resource.name, id);
+            }
+        }
+    }
+
+    if dry_run {
+        info!("Dry Run: No changes were applied.");
+        return Ok(());
+    }
+
+    backend.save(&state).await?;
+    let recorded = History::for_project(project, config.state_scope()).record(project, "rollback", &state)?;
+    info!("Recorded history snapshot {}", recorded.id);
+    if let Some(output_path) = &config.output_path {
+        output::generate_config(&state, universe_id, &project.resolve(output_path))?;
+    }
+
+    if snapshot.config_hash != recorded.config_hash {
+        warn!("The config has changed since this snapshot; the next run will re-apply it unless you revert it too{}",
+            snapshot.git_commit.as_deref().map(|c| format!(" (e.g. git checkout {} -- {})", c, project.config_path().display())).unwrap_or_default());
+    }
+    info!("Rollback complete!");
+    Ok(())
+}
+
+/// Metadata fields that differ between two recorded states of a resource
+fn resource_changes(current: &ResourceState, target: &ResourceState) -> Vec<&'static str> {
+    let mut changes = Vec::new();
+    if current.name != target.name { changes.push("name"); }
+    if current.description != target.description { changes.push("description"); }
+    if current.price != target.price { changes.push("price"); }
+    if current.price_tier != target.price_tier { changes.push("price_tier"); }
+    if current.is_for_sale != target.is_for_sale { changes.push("is_for_sale"); }
+    if current.is_enabled != target.is_enabled { changes.push("is_enabled"); }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_place_changes_only_include_set_fields() {
+        let place: PlaceConfig = serde_json::from_value(serde_json::json!({
+            "place_id": 1, "name": "Lobby", "max_players": 20
+        })).unwrap();
+        let mut stored = PlaceState { name: Some("Old Lobby".to_string()), description: Some("Hi".to_string()), ..Default::default() };
+        assert_eq!(place_changes(&stored, &place), [("name", "displayName"), ("max_players", "serverSize")]);
+
+        stored.name = place.name.clone();
+        stored.max_players = Some(20);
+        assert!(place_changes(&stored, &place).is_empty());
+    }
+
+    #[test]
+    fn test_user_restrictions_compare_with_active_bans() {
+        let config: crate::config::UserRestrictionConfig = serde_json::from_value(serde_json::json!({
+            "user_id": 42, "place_id": 7, "duration": "7d", "display_reason": "Exploiting"
+        })).unwrap();
+        let desired = config.to_state().unwrap();
+        assert_eq!(desired.duration.as_deref(), Some("604800s"));
+        assert_eq!(UserRestrictionState::key(desired.user_id, desired.place_id), "7/42");
+
+        let item = serde_json::json!({
+            "path": "universes/1/places/7/user-restrictions/42",
+            "gameJoinRestriction": {
+                "active": true, "duration": "604800s", "displayReason": "Exploiting",
+                "privateReason": "", "excludeAltAccounts": false, "startTime": "2026-01-01T00:00:00Z"
+            }
+        });
+        let live = active_restriction(&item).unwrap();
+        assert_eq!((live.user_id, live.place_id), (42, Some(7)));
+        assert!(live.same_terms(&desired));
+        assert!(restriction_changes(&live, &desired).is_empty());
+
+        let changed = UserRestrictionState { duration: None, exclude_alt_accounts: true, ..desired.clone() };
+        assert_eq!(restriction_changes(&live, &changed), vec!["duration", "exclude_alt_accounts"]);
+
+        let mut lifted = item.clone();
+        lifted["gameJoinRestriction"]["active"] = serde_json::json!(false);
+        assert!(active_restriction(&lifted).is_none());
+
+        let bad: crate::config::UserRestrictionConfig = serde_json::from_value(serde_json::json!({ "user_id": 1, "duration": "3y" })).unwrap();
+        assert!(bad.to_state().is_err());
+        let overflow: crate::config::UserRestrictionConfig = serde_json::from_value(serde_json::json!({ "user_id": 1, "duration": "99999999999999999w" })).unwrap();
+        assert!(format!("{:#}", overflow.to_state().unwrap_err()).contains("too long"));
+    }
+
+    #[test]
+    fn test_changed_resources_lists_new_and_updated_ids() {
+        let mut before = SyncState::default();
+        before.update_game_pass(1, "VIP".to_string(), None, Some(100), Some(true), None, None);
+        before.update_badge(7, "Welcome".to_string(), None, Some(true), None, None);
+
+        let mut after = before.clone();
+        assert_eq!(changed_resources(42, &before, &after), None);
+
+        // Only timestamps moved: not a change
+        after.record_sync(ResourceKind::Badge, 7, None, Some("2026-01-01T00:00:00Z".to_string()), false);
+        assert_eq!(changed_resources(42, &before, &after), None);
+
+        // Linked by name without changes: not announced
+        after.update_developer_product(6, "Gems".to_string(), None, Some(20), None, None);
+        after.record_sync(ResourceKind::DeveloperProduct, 6, None, None, false);
+        assert_eq!(changed_resources(42, &before, &after), None);
+
+        after.update_game_pass(1, "VIP".to_string(), None, Some(150), Some(true), None, None);
+        after.update_developer_product(5, "Coins".to_string(), None, Some(10), None, None);
+        after.record_sync(ResourceKind::DeveloperProduct, 5, None, None, true);
+        let message: serde_json::Value = serde_json::from_str(&changed_resources(42, &before, &after).unwrap()).unwrap();
+        assert_eq!(message, serde_json::json!({
+            "universe_id": 42,
+            "changed": { "game_passes": [1], "developer_products": [5] }
+        }));
+
+        for id in 100..400 {
+            after.update_developer_product(id, format!("Product {}", id), None, Some(1), None, None);
+            after.record_sync(ResourceKind::DeveloperProduct, id, None, None, true);
+        }
+        let message: serde_json::Value = serde_json::from_str(&changed_resources(42, &before, &after).unwrap()).unwrap();
+        assert_eq!(message["truncated"], true);
+    }
+
+    #[test]
+    fn test_confirmed_universe_only_records_echoed_values() {
+        let stored = UniverseState { name: Some("Old".to_string()), ..Default::default() };
+        let desired = UniverseState {
+            name: Some("New".to_string()),
+            genre: Some("adventure".to_string()),
+            playable_devices: Some(vec!["phone".to_string(), "computer".to_string()]),
+            private_server_cost: Some("100".to_string()),
+            ..Default::default()
+        };
+        let changes = ["name", "genre", "playable_devices", "private_server_cost"];
+        // Genre is normalized by Roblox, devices come back reordered and the
+        // private server fields are missing from the response
+        let response = serde_json::json!({
+            "name": "New",
+            "genre": "Adventure",
+            "playableDevices": ["Computer", "Phone"],
+        });
+
+        let confirmed = confirmed_universe(stored, &desired, &changes, &response);
+        assert_eq!(confirmed.name.as_deref(), Some("New"));
+        assert_eq!(confirmed.genre.as_deref(), Some("adventure"));
+        assert_eq!(confirmed.playable_devices, desired.playable_devices);
+        assert_eq!(confirmed.private_server_cost, None);
+        assert_eq!(universe_changes(Some(&confirmed), &desired), ["private_server_cost"]);
+    }
+
+    #[test]
+    fn test_resource_id_prefix_only_for_subscriptions() {
+        assert_eq!(resource_id(&serde_json::json!({ "id": "EXP-123" }), ResourceKind::Subscription), Some(123));
+        assert_eq!(resource_id(&serde_json::json!({ "gamePassId": "456" }), ResourceKind::GamePass), Some(456));
+        assert_eq!(resource_id(&serde_json::json!({ "id": "EXP-123" }), ResourceKind::GamePass), None);
+        assert_eq!(resource_id(&serde_json::json!({ "productId": "EXP-123" }), ResourceKind::DeveloperProduct), None);
+    }
+}
@@ -1,8 +1,11 @@
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::env;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use crate::state::{StateScope, UserRestrictionState};
 
 // --- Private Server Cost ---
 
@@ -114,34 +117,156 @@ impl Config {
     }
 }
 
-// --- YAML Configuration ---
+// --- File Formats ---
+
+/// Config file names searched for (in order) when no `--config` is given
+pub const CONFIG_FILE_NAMES: [&str; 4] = ["rblxsync.yml", "rblxsync.yaml", "rblxsync.toml", "rblxsync.json"];
+
+/// Serialization format of a config or lock file, detected from its extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Yaml,
+    Toml,
+    Json,
+}
+
+impl ConfigFormat {
+    /// Detect the format from a file extension (`.yml`/`.yaml`, `.toml`, `.json`)
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+        extension.parse()
+            .with_context(|| format!("Unsupported file extension for {:?} (expected .yml, .yaml, .toml or .json)", path))
+    }
+
+    /// File extension used when creating new files in this format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ConfigFormat::Yaml => "yml",
+            ConfigFormat::Toml => "toml",
+            ConfigFormat::Json => "json",
+        }
+    }
+
+    // TOML only allows string keys, so it goes through `serde_json::Value`,
+    // which stringifies the numeric IDs used as lock file keys (and parses them back).
+
+    pub fn parse<T: DeserializeOwned>(&self, content: &str) -> Result<T> {
+        Ok(match self {
+            ConfigFormat::Yaml => serde_yaml::from_str(content)?,
+            ConfigFormat::Toml => serde_json::from_value(toml::from_str::<serde_json::Value>(content)?)?,
+            ConfigFormat::Json => serde_json::from_str(content)?,
+        })
+    }
+
+    pub fn serialize<T: Serialize>(&self, value: &T) -> Result<String> {
+        Ok(match self {
+            ConfigFormat::Yaml => serde_yaml::to_string(value)?,
+            ConfigFormat::Toml => toml::to_string(&serde_json::to_value(value)?)?,
+            ConfigFormat::Json => serde_json::to_string_pretty(value)? + "\n",
+        })
+    }
+}
+
+impl FromStr for ConfigFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "yml" | "yaml" => Ok(ConfigFormat::Yaml),
+            "toml" => Ok(ConfigFormat::Toml),
+            "json" => Ok(ConfigFormat::Json),
+            _ => Err(anyhow!("unknown format '{}' (expected yaml, toml or json)", s)),
+        }
+    }
+}
+
+// --- Project Configuration ---
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct RblxSyncConfig {
     #[serde(default = "default_assets_dir")]
     pub assets_dir: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub creator: Option<CreatorConfig>,
     pub universe: UniverseConfig,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub game_passes: Vec<GamePassConfig>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub developer_products: Vec<DeveloperProductConfig>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub badges: Vec<BadgeConfig>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub subscriptions: Vec<SubscriptionConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub places: Vec<PlaceConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub data_stores: Vec<DataStoreConfig>,
+    /// Bans kept in sync with the universe; removing an entry lifts the ban
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub user_restrictions: Vec<UserRestrictionConfig>,
+    /// Ordered DataStores managed with the `leaderboard` commands
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ordered_data_stores: Vec<OrderedDataStoreConfig>,
     /// Payment source type for badge creation (costs 100 Robux per badge)
     /// Valid values: "user" (pay from user funds) or "group" (pay from group funds)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub badge_payment_source: Option<String>,
     /// Output path for generating Luau config from the lock file after sync
     /// e.g. "Config.luau" or "src/shared/Config.luau"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub output_path: Option<String>,
+    /// Where the sync state is stored (defaults to the local lock file)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<StateBackendConfig>,
+    /// MessagingService message published after a successful run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notify: Option<NotifyConfig>,
+    /// Environment name (e.g. "staging"), giving this config its own lock file
+    /// section when several configs target the same universe
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<String>,
 }
 
 fn default_assets_dir() -> String {
     "assets".to_string()
 }
 
+/// State backend selection, e.g. `state: { backend: http, url: "https://..." }`
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(tag = "backend", rename_all = "lowercase")]
+pub enum StateBackendConfig {
+    /// Lock file on disk (the default; see `--lock-file`)
+    Local,
+    /// Plain HTTP endpoint supporting GET/PUT with ETags
+    Http {
+        url: String,
+        /// Environment variable holding a bearer token
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        token_env: Option<String>,
+    },
+    /// S3 or S3-compatible object store
+    S3 {
+        bucket: String,
+        key: String,
+        #[serde(default = "default_s3_region")]
+        region: String,
+        /// Custom endpoint for S3-compatible stores (path-style addressing)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        endpoint: Option<String>,
+    },
+}
+
+fn default_s3_region() -> String {
+    "us-east-1".to_string()
+}
+
+/// Where to announce the resources changed by a run, so live servers can reload them
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct NotifyConfig {
+    /// MessagingService topic
+    pub topic: String,
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct CreatorConfig {
     pub id: String,
@@ -153,16 +278,34 @@ pub struct CreatorConfig {
 pub struct UniverseConfig {
     /// Universe ID (required)
     pub id: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub genre: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub playable_devices: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub max_players: Option<u32>,
     /// Private server cost: "disabled", 0 (free), or a positive number (Robux cost)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub private_server_cost: Option<PrivateServerCost>,
 }
 
 impl UniverseConfig {
+    pub fn new(id: u64) -> Self {
+        Self {
+            id,
+            name: None,
+            description: None,
+            genre: None,
+            playable_devices: None,
+            max_players: None,
+            private_server_cost: None,
+        }
+    }
+
     /// Check if any universe settings are defined
     pub fn has_settings(&self) -> bool {
         self.name.is_some() 
@@ -177,43 +320,723 @@ impl UniverseConfig {
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct GamePassConfig {
     pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub price: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub icon: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub is_for_sale: Option<bool>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct DeveloperProductConfig {
     pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
     pub price: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub icon: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub is_active: Option<bool>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct BadgeConfig {
     pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub icon: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub is_enabled: Option<bool>,
 }
 
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct SubscriptionConfig {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Price tier in USD, e.g. "4.99"
+    pub price_tier: String,
+    #[serde(default)]
+    pub period: SubscriptionPeriod,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub is_active: Option<bool>,
+}
+
+/// How often a subscription renews (Roblox currently only offers monthly subscriptions)
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SubscriptionPeriod {
+    #[default]
+    Monthly,
+}
+
+impl SubscriptionPeriod {
+    /// Name in the config and lock file
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SubscriptionPeriod::Monthly => "monthly",
+        }
+    }
+
+    /// Value expected by the subscriptions API
+    pub fn api_value(&self) -> &'static str {
+        match self {
+            SubscriptionPeriod::Monthly => "Monthly",
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct PlaceConfig {
     pub place_id: u64,
-    pub file_path: String,
+    /// Place file to publish (not needed for places whose settings are only synced)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_path: Option<String>,
     #[serde(default)]
     pub publish: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Maximum players per server for this place; for the start place, set either
+    /// this or `universe.max_players`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_players: Option<u32>,
+    /// Restart the universe's servers once every place has been published
+    #[serde(default)]
+    pub restart_servers: bool,
+    /// With `restart_servers`, only restart servers running an outdated version
+    #[serde(default)]
+    pub restart_outdated_only: bool,
+    /// Luau script (relative to the config file) run against the new version after
+    /// publishing, e.g. smoke tests; `publish` fails if it errors
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_publish_script: Option<String>,
+}
+
+/// Entries seeded into a standard DataStore
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct DataStoreConfig {
+    pub name: String,
+    #[serde(default = "default_data_store_scope")]
+    pub scope: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub entries: Vec<DataStoreEntryConfig>,
+}
+
+fn default_data_store_scope() -> String {
+    "global".to_string()
+}
+
+/// A DataStore key and its value, given inline or read from a file
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct DataStoreEntryConfig {
+    pub key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<serde_json::Value>,
+    /// JSON, YAML or TOML file holding the value (relative to the config file)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+}
+
+impl DataStoreEntryConfig {
+    /// The value to store, reading `file` if given
+    pub fn load_value(&self, project: &crate::project::Project) -> Result<serde_json::Value> {
+        match (&self.value, &self.file) {
+            (Some(value), None) => Ok(value.clone()),
+            (None, Some(file)) => {
+                let path = project.resolve(file);
+                let content = fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read DataStore value file {:?}", path))?;
+                ConfigFormat::from_path(&path)?.parse(&content)
+                    .with_context(|| format!("Failed to parse DataStore value file {:?}", path))
+            }
+            _ => Err(anyhow!("DataStore entry '{}' must set exactly one of `value` or `file`", self.key)),
+        }
+    }
+}
+
+/// A ban from the universe or from one of its places
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct UserRestrictionConfig {
+    pub user_id: u64,
+    /// How long the ban lasts, e.g. "12h", "7d" or "2w"; permanent if omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<String>,
+    /// Reason shown to the user
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_reason: Option<String>,
+    /// Reason only visible to the experience's moderators
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub private_reason: Option<String>,
+    #[serde(default)]
+    pub exclude_alt_accounts: bool,
+    /// Ban from this place only instead of the whole universe
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub place_id: Option<u64>,
+}
+
+impl UserRestrictionConfig {
+    /// The ban in the form recorded in the state
+    pub fn to_state(&self) -> Result<UserRestrictionState> {
+        let duration = match &self.duration {
+            Some(duration) => Some(format!("{}s", parse_duration(duration)
+                .with_context(|| format!("Invalid duration for user {}", self.user_id))?)),
+            None => None,
+        };
+        Ok(UserRestrictionState {
+            user_id: self.user_id,
+            place_id: self.place_id,
+            duration,
+            display_reason: self.display_reason.clone(),
+            private_reason: self.private_reason.clone(),
+            exclude_alt_accounts: self.exclude_alt_accounts,
+            start_time: None,
+        })
+    }
+}
+
+/// Parse a duration such as `90s`, `30m`, `12h`, `7d` or `2w` into seconds
+fn parse_duration(value: &str) -> Result<u64> {
+    let value = value.trim();
+    let index = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (number, unit) = value.split_at(index);
+    let number: u64 = number.parse().map_err(|_| anyhow!("invalid duration '{}'", value))?;
+    let unit_seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(anyhow!("invalid duration unit in '{}' (use s, m, h, d or w)", value)),
+    };
+    number.checked_mul(unit_seconds).ok_or_else(|| anyhow!("duration '{}' is too long", value))
+}
+
+/// An Ordered DataStore backing a leaderboard
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct OrderedDataStoreConfig {
+    pub name: String,
+    #[serde(default = "default_data_store_scope")]
+    pub scope: String,
+    /// Directory (relative to the config file) that `leaderboard reset` exports
+    /// the entries to before deleting them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archive_dir: Option<String>,
 }
 
 impl RblxSyncConfig {
+    /// Check if any place defines settings synced by `run`
+    pub fn has_place_settings(&self) -> bool {
+        self.places.iter().any(|p| p.name.is_some() || p.description.is_some() || p.max_players.is_some())
+    }
+
+    /// Create an empty config for the given universe
+    pub fn new(universe_id: u64) -> Self {
+        Self {
+            assets_dir: default_assets_dir(),
+            creator: None,
+            universe: UniverseConfig::new(universe_id),
+            game_passes: Vec::new(),
+            developer_products: Vec::new(),
+            badges: Vec::new(),
+            subscriptions: Vec::new(),
+            places: Vec::new(),
+            data_stores: Vec::new(),
+            ordered_data_stores: Vec::new(),
+            user_restrictions: Vec::new(),
+            badge_payment_source: None,
+            output_path: None,
+            state: None,
+            notify: None,
+            environment: None,
+        }
+    }
+
+    /// The lock file section this config reads and writes
+    pub fn state_scope(&self) -> StateScope {
+        StateScope::new(self.universe.id, self.environment.clone())
+    }
+
+    /// Load a YAML, TOML or JSON config file (format detected from the extension).
+    /// `generate:`, `defaults:` and `templates:` are expanded into the resource
+    /// lists, so the returned config only contains fully resolved resources.
     pub fn load(path: &Path) -> Result<Self> {
+        let format = ConfigFormat::from_path(path)?;
         let content = fs::read_to_string(path)
             .with_context(|| format!("Failed to read config file at {:?}", path))?;
-        let config: RblxSyncConfig = serde_yaml::from_str(&content)
-            .context("Failed to parse config file")?;
+        let config = Self::parse(format, &content)
+            .with_context(|| format!("Failed to parse config file at {:?}", path))?;
         Ok(config)
     }
+
+    /// Parse config content, expanding `generate:` blocks, defaults and templates
+    pub fn parse(format: ConfigFormat, content: &str) -> Result<Self> {
+        let mut raw: serde_json::Value = format.parse(content)?;
+        expand_generators(&mut raw)?;
+        expand_templates(&mut raw)?;
+        Ok(serde_json::from_value(raw)?)
+    }
+
+    /// Write the config in the format matching the file extension
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let format = ConfigFormat::from_path(path)?;
+        fs::write(path, format.serialize(self)?)
+            .with_context(|| format!("Failed to write config file at {:?}", path))?;
+        Ok(())
+    }
+
+    /// Find the first existing config file in `dir`, trying each of `CONFIG_FILE_NAMES`
+    pub fn discover(dir: &Path) -> Option<PathBuf> {
+        CONFIG_FILE_NAMES.iter()
+            .map(|name| dir.join(name))
+            .find(|path| path.is_file())
+    }
+}
+
+// --- Generated Resources ---
+
+/// Resource sections that `generate:` can produce entries for
+const GENERATE_SECTIONS: [&str; 2] = ["game_passes", "developer_products"];
+
+/// Expand each `generate:` block into concrete resource entries.
+///
+/// Every combination of the `matrix` variables is rendered through `template`,
+/// substituting `{var}` (or `{var.field}` for mapping values) in string fields.
+/// Generated entries are appended after the hand-written ones and may still
+/// `extends:` a template.
+fn expand_generators(raw: &mut serde_json::Value) -> Result<()> {
+    use serde_json::{Map, Value};
+
+    let root = raw.as_object_mut().ok_or_else(|| anyhow!("config must be a mapping"))?;
+    let generators = match root.remove("generate") {
+        Some(Value::Array(list)) => list,
+        Some(Value::Null) | None => return Ok(()),
+        Some(_) => return Err(anyhow!("`generate` must be a list")),
+    };
+
+    for (index, generator) in generators.into_iter().enumerate() {
+        let Value::Object(mut generator) = generator else {
+            return Err(anyhow!("generate[{}] must be a mapping", index));
+        };
+
+        let resource = match generator.remove("resource") {
+            Some(Value::String(r)) if GENERATE_SECTIONS.contains(&r.as_str()) => r,
+            other => return Err(anyhow!(
+                "generate[{}].resource must be one of {:?}, got {}",
+                index, GENERATE_SECTIONS, other.map(|v| v.to_string()).unwrap_or_else(|| "nothing".to_string())
+            )),
+        };
+        let Some(Value::Object(matrix)) = generator.remove("matrix") else {
+            return Err(anyhow!("generate[{}].matrix must be a mapping of variable to list of values", index));
+        };
+        let Some(Value::Object(template)) = generator.remove("template") else {
+            return Err(anyhow!("generate[{}].template must be a mapping of resource fields", index));
+        };
+        if let Some(key) = generator.keys().next() {
+            return Err(anyhow!("generate[{}]: unknown field '{}'", index, key));
+        }
+
+        if matrix.is_empty() {
+            return Err(anyhow!("generate[{}].matrix must define at least one variable", index));
+        }
+
+        // Cartesian product of all matrix variables
+        let mut combinations = vec![Map::new()];
+        for (variable, values) in &matrix {
+            let Value::Array(values) = values else {
+                return Err(anyhow!("generate[{}].matrix.{} must be a list", index, variable));
+            };
+            combinations = combinations.iter()
+                .flat_map(|combination| values.iter().map(move |value| {
+                    let mut next = combination.clone();
+                    next.insert(variable.clone(), value.clone());
+                    next
+                }))
+                .collect();
+        }
+
+        let mut entries = Vec::with_capacity(combinations.len());
+        for combination in &combinations {
+            let entry = render_value(&Value::Object(template.clone()), combination)
+                .with_context(|| format!("generate[{}]", index))?;
+            entries.push(entry);
+        }
+
+        match root.entry(resource.clone()).or_insert_with(|| Value::Array(Vec::new())) {
+            Value::Array(list) => list.extend(entries),
+            _ => return Err(anyhow!("`{}` must be a list", resource)),
+        }
+    }
+
+    Ok(())
+}
+
+/// Substitute matrix variables into every string within a template value
+fn render_value(value: &serde_json::Value, vars: &serde_json::Map<String, serde_json::Value>) -> Result<serde_json::Value> {
+    use serde_json::Value;
+
+    Ok(match value {
+        Value::String(s) => render_string(s, vars)?,
+        Value::Array(items) => Value::Array(items.iter().map(|v| render_value(v, vars)).collect::<Result<_>>()?),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| Ok((k.clone(), render_value(v, vars)?)))
+                .collect::<Result<_>>()?,
+        ),
+        other => other.clone(),
+    })
+}
+
+/// Render a template string. A string that is exactly one `{placeholder}` keeps
+/// the variable's type, so `price: "{tier.price}"` produces a number.
+/// `{var:,}` formats numbers with thousands separators; `{{`/`}}` are literal braces.
+fn render_string(template: &str, vars: &serde_json::Map<String, serde_json::Value>) -> Result<serde_json::Value> {
+    use serde_json::Value;
+
+    if let Some(expr) = template.strip_prefix('{').and_then(|t| t.strip_suffix('}')) {
+        if !expr.contains(['{', '}', ':']) {
+            return lookup_variable(expr, vars).cloned();
+        }
+    }
+
+    let mut output = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                output.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                output.push('}');
+            }
+            '{' => {
+                let mut expr = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => expr.push(c),
+                        None => return Err(anyhow!("unterminated `{{` in template '{}'", template)),
+                    }
+                }
+                let (path, spec) = match expr.split_once(':') {
+                    Some((path, spec)) => (path, Some(spec)),
+                    None => (expr.as_str(), None),
+                };
+                let value = lookup_variable(path.trim(), vars)?;
+                output.push_str(&format_variable(value, spec)?);
+            }
+            '}' => return Err(anyhow!("unmatched `}}` in template '{}' (use `}}}}` for a literal brace)", template)),
+            _ => output.push(c),
+        }
+    }
+    Ok(Value::String(output))
+}
+
+fn lookup_variable<'a>(path: &str, vars: &'a serde_json::Map<String, serde_json::Value>) -> Result<&'a serde_json::Value> {
+    let mut parts = path.split('.');
+    let name = parts.next().unwrap_or_default();
+    let mut value = vars.get(name).ok_or_else(|| anyhow!("unknown matrix variable '{}'", name))?;
+    for field in parts {
+        value = value.get(field).ok_or_else(|| anyhow!("matrix variable '{}' has no field '{}'", name, field))?;
+    }
+    Ok(value)
+}
+
+fn format_variable(value: &serde_json::Value, spec: Option<&str>) -> Result<String> {
+    use serde_json::Value;
+
+    match (value, spec) {
+        (Value::String(s), None) => Ok(s.clone()),
+        (Value::Number(n), None) => Ok(n.to_string()),
+        (Value::Bool(b), None) => Ok(b.to_string()),
+        (Value::Number(n), Some(",")) => {
+            let digits = n.to_string();
+            let (sign, digits) = digits.split_at(if digits.starts_with('-') { 1 } else { 0 });
+            let (whole, fraction) = digits.split_once('.').map_or((digits, None), |(w, f)| (w, Some(f)));
+            let mut grouped = String::new();
+            for (i, c) in whole.chars().enumerate() {
+                if i > 0 && (whole.len() - i) % 3 == 0 {
+                    grouped.push(',');
+                }
+                grouped.push(c);
+            }
+            Ok(match fraction {
+                Some(f) => format!("{}{}.{}", sign, grouped, f),
+                None => format!("{}{}", sign, grouped),
+            })
+        }
+        (_, Some(spec)) => Err(anyhow!("unsupported format '{}' for value {}", spec, value)),
+        (_, None) => Err(anyhow!("cannot substitute {} into a string", value)),
+    }
+}
+
+// --- Defaults & Templates ---
+
+/// Resource sections that support `defaults:` and `extends:`
+const TEMPLATE_SECTIONS: [&str; 4] = ["game_passes", "developer_products", "badges", "subscriptions"];
+
+/// Expand the `defaults:` and `templates:` sections into every resource entry.
+///
+/// Fields are layered as: per-type defaults, then the `extends:` template chain
+/// (base templates first), then the entry itself. A `description_suffix` field
+/// at any layer is appended to the resolved description.
+fn expand_templates(raw: &mut serde_json::Value) -> Result<()> {
+    use serde_json::{Map, Value};
+
+    let root = raw.as_object_mut().ok_or_else(|| anyhow!("config must be a mapping"))?;
+
+    let defaults = match root.remove("defaults") {
+        Some(Value::Object(map)) => map,
+        Some(Value::Null) | None => Map::new(),
+        Some(_) => return Err(anyhow!("`defaults` must be a mapping of resource type to fields")),
+    };
+    for section in defaults.keys() {
+        if !TEMPLATE_SECTIONS.contains(&section.as_str()) {
+            return Err(anyhow!("unknown resource type in `defaults`: '{}' (expected one of {:?})", section, TEMPLATE_SECTIONS));
+        }
+    }
+
+    let templates = match root.remove("templates") {
+        Some(Value::Object(map)) => map,
+        Some(Value::Null) | None => Map::new(),
+        Some(_) => return Err(anyhow!("`templates` must be a mapping of template name to fields")),
+    };
+
+    for section in TEMPLATE_SECTIONS {
+        let Some(Value::Array(entries)) = root.get_mut(section) else { continue };
+        let section_defaults = match defaults.get(section) {
+            Some(Value::Object(map)) => map.clone(),
+            Some(_) => return Err(anyhow!("`defaults.{}` must be a mapping", section)),
+            None => Map::new(),
+        };
+
+        for (index, entry) in entries.iter_mut().enumerate() {
+            let Value::Object(fields) = entry else {
+                return Err(anyhow!("{}[{}] must be a mapping", section, index));
+            };
+
+            let mut resolved = section_defaults.clone();
+            let mut suffix = take_suffix(&mut resolved);
+            if let Some(extends) = fields.remove("extends") {
+                let name = extends.as_str()
+                    .ok_or_else(|| anyhow!("{}[{}].extends must be a template name", section, index))?;
+                let mut template = resolve_template(name, &templates, &mut Vec::new())?;
+                suffix = take_suffix(&mut template).or(suffix);
+                resolved.extend(template);
+            }
+            suffix = take_suffix(fields).or(suffix);
+            resolved.extend(std::mem::take(fields));
+
+            if let Some(suffix) = suffix {
+                let description = match resolved.get("description").and_then(|d| d.as_str()) {
+                    Some(base) if !base.is_empty() => format!("{}{}", base, suffix),
+                    _ => suffix.trim_start().to_string(),
+                };
+                resolved.insert("description".to_string(), Value::String(description));
+            }
+
+            *fields = resolved;
+        }
+    }
+
+    Ok(())
+}
+
+/// Resolve a named template, following its `extends:` chain
+fn resolve_template(
+    name: &str,
+    templates: &serde_json::Map<String, serde_json::Value>,
+    chain: &mut Vec<String>,
+) -> Result<serde_json::Map<String, serde_json::Value>> {
+    if chain.iter().any(|n| n == name) {
+        chain.push(name.to_string());
+        return Err(anyhow!("template inheritance cycle: {}", chain.join(" -> ")));
+    }
+    chain.push(name.to_string());
+
+    let mut template = match templates.get(name) {
+        Some(serde_json::Value::Object(map)) => map.clone(),
+        Some(_) => return Err(anyhow!("template '{}' must be a mapping", name)),
+        None => return Err(anyhow!("unknown template '{}'", name)),
+    };
+
+    let Some(parent) = template.remove("extends") else { return Ok(template) };
+    let parent = parent.as_str()
+        .ok_or_else(|| anyhow!("templates.{}.extends must be a template name", name))?;
+    let mut resolved = resolve_template(parent, templates, chain)?;
+
+    // A child's suffix replaces its parent's rather than stacking
+    if template.contains_key("description_suffix") {
+        resolved.remove("description_suffix");
+    }
+    resolved.extend(template);
+    Ok(resolved)
+}
+
+fn take_suffix(fields: &mut serde_json::Map<String, serde_json::Value>) -> Option<String> {
+    fields.remove("description_suffix").and_then(|s| s.as_str().map(String::from))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const YAML: &str = r#"
+universe:
+  id: 123
+  name: "Test"
+  private_server_cost: "disabled"
+game_passes:
+  - name: "VIP"
+    price: 100
+developer_products:
+  - name: "Coins"
+    price: 10
+output_path: "Config.luau"
+"#;
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(ConfigFormat::from_path(Path::new("rblxsync.yml")).unwrap(), ConfigFormat::Yaml);
+        assert_eq!(ConfigFormat::from_path(Path::new("a/rblxsync.yaml")).unwrap(), ConfigFormat::Yaml);
+        assert_eq!(ConfigFormat::from_path(Path::new("rblxsync.toml")).unwrap(), ConfigFormat::Toml);
+        assert_eq!(ConfigFormat::from_path(Path::new("rblxsync.json")).unwrap(), ConfigFormat::Json);
+        assert!(ConfigFormat::from_path(Path::new("rblxsync.txt")).is_err());
+    }
+
+    #[test]
+    fn test_round_trip_all_formats() {
+        let config: RblxSyncConfig = ConfigFormat::Yaml.parse(YAML).unwrap();
+
+        for format in [ConfigFormat::Yaml, ConfigFormat::Toml, ConfigFormat::Json] {
+            let content = format.serialize(&config).unwrap();
+            let parsed: RblxSyncConfig = format.parse(&content).unwrap();
+            assert_eq!(parsed.universe.id, 123);
+            assert_eq!(parsed.universe.private_server_cost, Some(PrivateServerCost::Disabled));
+            assert_eq!(parsed.game_passes[0].price, Some(100));
+            assert_eq!(parsed.developer_products[0].name, "Coins");
+            assert_eq!(parsed.output_path.as_deref(), Some("Config.luau"));
+        }
+    }
+
+    #[test]
+    fn test_defaults_and_templates() {
+        let config = RblxSyncConfig::parse(ConfigFormat::Yaml, r#"
+universe:
+  id: 1
+defaults:
+  developer_products:
+    is_active: true
+    icon: "coin.png"
+    description_suffix: " Purchases are final."
+templates:
+  coin_pack:
+    price: 10
+    description: "A pack of coins."
+  big_coin_pack:
+    extends: coin_pack
+    price: 100
+    icon: "big_coin.png"
+developer_products:
+  - name: "Small"
+    extends: coin_pack
+  - name: "Big"
+    extends: big_coin_pack
+    is_active: false
+  - name: "Plain"
+    price: 5
+"#).unwrap();
+
+        let products = &config.developer_products;
+        assert_eq!(products[0].price, 10);
+        assert_eq!(products[0].icon.as_deref(), Some("coin.png"));
+        assert_eq!(products[0].is_active, Some(true));
+        assert_eq!(products[0].description.as_deref(), Some("A pack of coins. Purchases are final."));
+        assert_eq!(products[1].price, 100);
+        assert_eq!(products[1].icon.as_deref(), Some("big_coin.png"));
+        assert_eq!(products[1].is_active, Some(false));
+        assert_eq!(products[2].description.as_deref(), Some("Purchases are final."));
+    }
+
+    #[test]
+    fn test_generate_matrix() {
+        let config = RblxSyncConfig::parse(ConfigFormat::Yaml, r#"
+universe: { id: 1 }
+templates:
+  coins: { is_active: true }
+developer_products:
+  - { name: "Starter Pack", price: 5 }
+generate:
+  - resource: developer_products
+    matrix:
+      tier:
+        - { coins: 100, price: 10 }
+        - { coins: 50000, price: 2500 }
+    template:
+      extends: coins
+      name: "{tier.coins:,} Coins"
+      description: "Get {tier.coins:,} coins {{instantly}}"
+      price: "{tier.price}"
+      icon: "coins_{tier.coins}.png"
+  - resource: game_passes
+    matrix:
+      perk: ["Speed", "Jump"]
+      level: [1, 2]
+    template:
+      name: "{perk} {level}"
+"#).unwrap();
+
+        let products = &config.developer_products;
+        assert_eq!(products.len(), 3);
+        assert_eq!(products[0].name, "Starter Pack");
+        assert_eq!(products[2].name, "50,000 Coins");
+        assert_eq!(products[2].description.as_deref(), Some("Get 50,000 coins {instantly}"));
+        assert_eq!(products[2].price, 2500);
+        assert_eq!(products[2].icon.as_deref(), Some("coins_50000.png"));
+        assert_eq!(products[2].is_active, Some(true));
+
+        let passes: Vec<&str> = config.game_passes.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(passes, ["Speed 1", "Speed 2", "Jump 1", "Jump 2"]);
+    }
+
+    #[test]
+    fn test_template_errors() {
+        let unknown = RblxSyncConfig::parse(ConfigFormat::Yaml, r#"
+universe: { id: 1 }
+badges:
+  - { name: "A", extends: missing }
+"#);
+        assert!(unknown.unwrap_err().to_string().contains("unknown template 'missing'"));
+
+        let cycle = RblxSyncConfig::parse(ConfigFormat::Yaml, r#"
+universe: { id: 1 }
+templates:
+  a: { extends: b }
+  b: { extends: a }
+badges:
+  - { name: "A", extends: a }
+"#);
+        assert!(cycle.unwrap_err().to_string().contains("a -> b -> a"));
+
+        let generate = |name: &str| RblxSyncConfig::parse(ConfigFormat::Yaml, &format!(r#"
+universe: {{ id: 1 }}
+generate:
+  - resource: game_passes
+    matrix: {{ tier: [{{ name: Gold }}] }}
+    template: {{ name: "{}", price: 100 }}
+"#, name));
+        let unterminated = generate("Tier {tier.name");
+        assert!(format!("{:#}", unterminated.unwrap_err()).contains("unterminated `{` in template 'Tier {tier.name'"));
+        let unmatched = generate("Tier tier.name}");
+        assert!(format!("{:#}", unmatched.unwrap_err()).contains("unmatched `}`"));
+    }
 }
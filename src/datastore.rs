@@ -0,0 +1,351 @@
+//! Backup and restore of standard DataStores through Open Cloud.
+//!
+//! A backup is a directory holding a `manifest.json` and one JSON file per entry
+//! at `<store>/<scope>/<key>.json` (each name percent-encoded), with the value
+//! and its version, timestamps, attributes and user IDs. Entries are transferred
+//! concurrently; an interrupted backup or restore continues where it stopped
+//! when run again with `--resume`.
+
+use crate::api::RobloxClient;
+use crate::commands::list_all;
+use crate::config::ConfigFormat;
+use crate::state::write_atomic;
+use anyhow::{anyhow, Context, Result};
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// Which entries to back up or restore
+#[derive(Debug, Clone, Default)]
+pub struct EntryFilter {
+    /// DataStore names; all stores if empty
+    pub stores: Vec<String>,
+    pub scope: Option<String>,
+    pub prefix: Option<String>,
+}
+
+impl EntryFilter {
+    fn matches(&self, data_store: &str, scope: &str, key: &str) -> bool {
+        (self.stores.is_empty() || self.stores.iter().any(|s| s == data_store))
+            && self.scope.as_deref().is_none_or(|s| s == scope)
+            && self.prefix.as_deref().is_none_or(|p| key.starts_with(p))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TransferOptions {
+    /// Maximum number of entries read or written at the same time
+    pub concurrency: usize,
+    /// Skip entries an earlier, interrupted run already transferred
+    pub resume: bool,
+    pub dry_run: bool,
+}
+
+/// One backed-up entry, as stored in its file
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BackupEntry {
+    pub data_store: String,
+    pub scope: String,
+    pub key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_time: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_time: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_md5: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attributes: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_ids: Option<serde_json::Value>,
+    pub value: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Manifest {
+    universe_id: u64,
+    started_at: String,
+    /// Set once every entry was written; missing for an interrupted backup
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    completed_at: Option<String>,
+    #[serde(default)]
+    entries: usize,
+}
+
+impl Manifest {
+    fn read(dir: &Path) -> Result<Option<Self>> {
+        let path = dir.join(MANIFEST_FILE);
+        match fs::read_to_string(&path) {
+            Ok(content) => Ok(Some(ConfigFormat::Json.parse(&content)
+                .with_context(|| format!("Failed to parse backup manifest {:?}", path))?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).with_context(|| format!("Failed to read backup manifest {:?}", path)),
+        }
+    }
+
+    fn write(&self, dir: &Path) -> Result<()> {
+        write_atomic(&dir.join(MANIFEST_FILE), ConfigFormat::Json.serialize(self)?.as_bytes())
+    }
+}
+
+/// Download every matching entry of the universe's DataStores into `dir`
+pub async fn backup(client: &RobloxClient, universe_id: u64, dir: &Path, filter: &EntryFilter, options: TransferOptions) -> Result<()> {
+    let started_at = now();
+    match Manifest::read(dir)? {
+        Some(manifest) if options.resume && manifest.universe_id != universe_id => {
+            return Err(anyhow!("{} holds a backup of universe {}, not {}", dir.display(), manifest.universe_id, universe_id));
+        }
+        Some(_) if options.resume => {}
+        Some(_) => return Err(anyhow!("{} already holds a backup (use --resume to continue an interrupted one)", dir.display())),
+        None if !options.dry_run => {
+            fs::create_dir_all(dir).with_context(|| format!("Failed to create backup directory {:?}", dir))?;
+            Manifest { universe_id, started_at: started_at.clone(), completed_at: None, entries: 0 }.write(dir)?;
+        }
+        None => {}
+    }
+
+    let stores = if filter.stores.is_empty() {
+        list_all(|cursor| client.list_data_stores(universe_id, None, cursor)).await?
+            .iter()
+            .filter_map(|store| store["name"].as_str().map(String::from))
+            .collect()
+    } else {
+        filter.stores.clone()
+    };
+
+    let semaphore = Arc::new(Semaphore::new(options.concurrency.max(1)));
+    let mut tasks = JoinSet::new();
+    let mut skipped = 0;
+    for store in stores {
+        info!("Listing DataStore {}...", store);
+        let keys = list_all(|cursor| {
+            client.list_data_store_entries(universe_id, &store, filter.scope.as_deref(), filter.prefix.as_deref(), cursor)
+        }).await?;
+
+        for item in keys {
+            let (Some(scope), Some(key)) = (item["scope"].as_str(), item["key"].as_str()) else { continue };
+            let path = entry_path(dir, &store, scope, key);
+            if path.exists() {
+                skipped += 1;
+                continue;
+            }
+            if options.dry_run {
+                info!("  [BACKUP] {}/{}/{}", store, scope, key);
+                continue;
+            }
+
+            let (client, semaphore) = (client.clone(), semaphore.clone());
+            let (store, scope, key) = (store.clone(), scope.to_string(), key.to_string());
+            tasks.spawn(async move {
+                let _permit = semaphore.acquire_owned().await?;
+                let label = format!("{}/{}/{}", store, scope, key);
+                let entry = client.get_data_store_entry(universe_id, &store, &scope, &key).await
+                    .with_context(|| label.clone())?;
+                // Deleted between listing and reading
+                let Some(entry) = entry else { return Ok(false) };
+                let backup = BackupEntry {
+                    data_store: store,
+                    scope,
+                    key,
+                    version: entry.version,
+                    created_time: entry.created_time,
+                    updated_time: entry.updated_time,
+                    content_md5: entry.content_md5,
+                    attributes: entry.attributes,
+                    user_ids: entry.user_ids,
+                    value: entry.value,
+                };
+                fs::create_dir_all(path.parent().unwrap_or(Path::new(".")))?;
+                write_atomic(&path, ConfigFormat::Json.serialize(&backup)?.as_bytes())?;
+                debug!("  [BACKED UP] {}", label);
+                Ok(true)
+            });
+        }
+    }
+
+    let (written, failed) = join_all(tasks).await;
+    if options.dry_run {
+        info!("Dry Run: {} entries already backed up would be skipped.", skipped);
+        return Ok(());
+    }
+    info!("DataStore Backup Summary: {} backed up, {} skipped (already backed up), {} failed", written, skipped, failed);
+    if failed > 0 {
+        return Err(anyhow!("{} entries could not be backed up; run again with --resume to retry them", failed));
+    }
+
+    let mut manifest = Manifest::read(dir)?.unwrap_or(Manifest { universe_id, started_at, completed_at: None, entries: 0 });
+    manifest.completed_at = Some(now());
+    manifest.entries = backup_files(dir)?.len();
+    manifest.write(dir)
+}
+
+/// Write the matching entries of the backup in `dir` to the universe's DataStores.
+/// A backup of another universe is only restored with `allow_other_universe`.
+pub async fn restore(client: &RobloxClient, universe_id: u64, dir: &Path, allow_other_universe: bool, filter: &EntryFilter, options: TransferOptions) -> Result<()> {
+    let manifest = Manifest::read(dir)?
+        .ok_or_else(|| anyhow!("{} is not a DataStore backup (no {})", dir.display(), MANIFEST_FILE))?;
+    if manifest.completed_at.is_none() {
+        warn!("The backup in {} is incomplete", dir.display());
+    }
+    if manifest.universe_id != universe_id {
+        if !allow_other_universe {
+            return Err(anyhow!(
+                "{} is a backup of universe {}, not {}. Pass --allow-other-universe to restore it anyway.",
+                dir.display(), manifest.universe_id, universe_id
+            ));
+        }
+        warn!("Restoring a backup of universe {} into universe {}", manifest.universe_id, universe_id);
+    }
+
+    // Restored entries are appended to a progress file, which a resumed restore skips
+    let progress_path = dir.join(format!("restore-{}.progress", universe_id));
+    let done: HashSet<String> = if options.resume {
+        fs::read_to_string(&progress_path).unwrap_or_default().lines().map(String::from).collect()
+    } else {
+        HashSet::new()
+    };
+    let progress = if options.dry_run {
+        None
+    } else {
+        let file = fs::OpenOptions::new().create(true).write(true).append(options.resume).truncate(!options.resume)
+            .open(&progress_path)
+            .with_context(|| format!("Failed to open {:?}", progress_path))?;
+        Some(Arc::new(Mutex::new(file)))
+    };
+
+    let semaphore = Arc::new(Semaphore::new(options.concurrency.max(1)));
+    let mut tasks = JoinSet::new();
+    let mut skipped = 0;
+    for path in backup_files(dir)? {
+        let relative = path.strip_prefix(dir).unwrap_or(&path).to_string_lossy().to_string();
+        if done.contains(&relative) {
+            skipped += 1;
+            continue;
+        }
+        let content = fs::read_to_string(&path).with_context(|| format!("Failed to read {:?}", path))?;
+        let entry: BackupEntry = ConfigFormat::Json.parse(&content).with_context(|| format!("Failed to parse {:?}", path))?;
+        if !filter.matches(&entry.data_store, &entry.scope, &entry.key) {
+            continue;
+        }
+        let label = format!("{}/{}/{}", entry.data_store, entry.scope, entry.key);
+        let Some(progress) = progress.clone() else {
+            info!("  [RESTORE] {}", label);
+            continue;
+        };
+
+        let (client, semaphore) = (client.clone(), semaphore.clone());
+        tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await?;
+            client.set_data_store_entry(
+                universe_id, &entry.data_store, &entry.scope, &entry.key, &entry.value,
+                entry.attributes.as_ref(), entry.user_ids.as_ref(),
+            ).await.with_context(|| label.clone())?;
+            writeln!(progress.lock().map_err(|_| anyhow!("progress file lock poisoned"))?, "{}", relative)?;
+            debug!("  [RESTORED] {}", label);
+            Ok(true)
+        });
+    }
+
+    let (written, failed) = join_all(tasks).await;
+    if options.dry_run {
+        info!("Dry Run: {} entries already restored would be skipped.", skipped);
+        return Ok(());
+    }
+    info!("DataStore Restore Summary: {} restored, {} skipped (already restored), {} failed", written, skipped, failed);
+    if failed > 0 {
+        return Err(anyhow!("{} entries could not be restored; run again with --resume to retry them", failed));
+    }
+    let _ = fs::remove_file(&progress_path);
+    Ok(())
+}
+
+/// Wait for all transfers, logging failures; returns (transferred, failed)
+pub(crate) async fn join_all(mut tasks: JoinSet<Result<bool>>) -> (usize, usize) {
+    let (mut written, mut failed) = (0, 0);
+    while let Some(result) = tasks.join_next().await {
+        match result.map_err(anyhow::Error::from).and_then(|r| r) {
+            Ok(true) => written += 1,
+            Ok(false) => {}
+            Err(e) => {
+                error!("  [FAILED] {:#}", e);
+                failed += 1;
+            }
+        }
+    }
+    (written, failed)
+}
+
+/// File of an entry within a backup directory
+fn entry_path(dir: &Path, data_store: &str, scope: &str, key: &str) -> PathBuf {
+    dir.join(encode_name(data_store)).join(encode_name(scope)).join(format!("{}.json", encode_name(key)))
+}
+
+/// All entry files of a backup, in a stable order
+fn backup_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for store in fs::read_dir(dir).with_context(|| format!("Failed to read backup directory {:?}", dir))? {
+        let store = store?.path();
+        if !store.is_dir() {
+            continue;
+        }
+        for scope in fs::read_dir(&store)? {
+            let scope = scope?.path();
+            if !scope.is_dir() {
+                continue;
+            }
+            for entry in fs::read_dir(&scope)? {
+                let entry = entry?.path();
+                if entry.extension().is_some_and(|ext| ext == "json") {
+                    files.push(entry);
+                }
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Percent-encode everything but ASCII letters, digits, `-` and `_`, so any
+/// DataStore, scope or key name is a valid file name on every platform
+fn encode_name(name: &str) -> String {
+    name.bytes()
+        .map(|b| match b {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'_' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn now() -> String {
+    chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_paths_and_filter() {
+        let dir = Path::new("backup");
+        assert_eq!(
+            entry_path(dir, "Player Data", "global", "user/1.v2"),
+            dir.join("Player%20Data").join("global").join("user%2F1%2Ev2.json")
+        );
+        assert_eq!(encode_name("..").len(), 6);
+
+        let filter = EntryFilter { stores: vec!["Shop".to_string()], scope: None, prefix: Some("item_".to_string()) };
+        assert!(filter.matches("Shop", "global", "item_1"));
+        assert!(!filter.matches("Shop", "global", "coins"));
+        assert!(!filter.matches("Players", "global", "item_1"));
+        assert!(EntryFilter::default().matches("Players", "live", "anything"));
+    }
+}
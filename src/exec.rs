@@ -0,0 +1,112 @@
+//! Running Luau scripts in a live server through Open Cloud Luau execution tasks,
+//! for `rblxsync exec` and the `post_publish_script` of places.
+
+use crate::api::{LuauTask, RobloxClient};
+use anyhow::{anyhow, Context, Result};
+use log::{debug, error, info, warn};
+use std::path::Path;
+use std::time::Duration;
+
+/// How long a script may run when no timeout is given (Roblox's own default)
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(300);
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Run the Luau script at `path` against a place version (the latest if `None`),
+/// printing its logs as they arrive and its return values at the end. Fails if
+/// the script errors or does not finish in time.
+pub async fn run_script(client: &RobloxClient, universe_id: u64, place_id: u64, version: Option<u64>, path: &Path, timeout: Option<Duration>) -> Result<()> {
+    let script = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read script {:?}", path))?;
+    let timeout = timeout.unwrap_or(DEFAULT_TIMEOUT);
+
+    let version_label = version.map(|v| format!("version {}", v)).unwrap_or_else(|| "latest version".to_string());
+    info!("Running {} on place {} ({})", path.display(), place_id, version_label);
+    let mut task = client.create_luau_task(universe_id, place_id, version, &script, Some(timeout.as_secs())).await?;
+    debug!("Created Luau task {}", task.path);
+
+    // Queueing for a server counts against the deadline too, so allow some slack
+    let deadline = tokio::time::Instant::now() + timeout + Duration::from_secs(60);
+    let mut printed = 0;
+    loop {
+        let finished = matches!(task.state.as_str(), "COMPLETE" | "FAILED" | "CANCELLED");
+        printed += print_new_logs(client, &task, printed).await?;
+        if finished {
+            break;
+        }
+        if tokio::time::Instant::now() >= deadline {
+            return Err(anyhow!("Luau task {} did not finish within {}s (state: {})", task.path, timeout.as_secs(), task.state));
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+        task = client.get_luau_task(&task.path).await?;
+    }
+
+    if let Some(results) = task_outcome(&task, path)? {
+        println!("{}", results);
+    }
+    info!("Script {} completed", path.display());
+    Ok(())
+}
+
+/// The outcome of a finished task: its return values as pretty-printed JSON
+/// (`None` if the script returned nothing), or the error it failed with
+fn task_outcome(task: &LuauTask, path: &Path) -> Result<Option<String>> {
+    match task.state.as_str() {
+        "COMPLETE" => {
+            let results = task.output.as_ref().map(|o| o["results"].clone()).unwrap_or_default();
+            if results.is_null() || results.as_array().is_some_and(|r| r.is_empty()) {
+                return Ok(None);
+            }
+            Ok(Some(serde_json::to_string_pretty(&results)?))
+        }
+        state => {
+            let message = task.error.as_ref()
+                .and_then(|e| e["message"].as_str())
+                .unwrap_or("no error message");
+            Err(anyhow!("Script {} {}: {}", path.display(), state.to_lowercase(), message))
+        }
+    }
+}
+
+/// Print the log messages after the first `skip`; returns how many were printed
+async fn print_new_logs(client: &RobloxClient, task: &LuauTask, skip: usize) -> Result<usize> {
+    let pages = crate::commands::list_all(|token| client.list_luau_task_logs(&task.path, token)).await?;
+    let messages: Vec<&serde_json::Value> = pages.iter()
+        .flat_map(|page| page["structuredMessages"].as_array().into_iter().flatten())
+        .collect();
+
+    for message in messages.iter().skip(skip) {
+        let text = message["message"].as_str().unwrap_or_default();
+        match message["messageType"].as_str() {
+            Some("WARNING") => warn!("  [LUAU] {}", text),
+            Some("ERROR") => error!("  [LUAU] {}", text),
+            _ => info!("  [LUAU] {}", text),
+        }
+    }
+    Ok(messages.len().saturating_sub(skip))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(state: &str, output: Option<serde_json::Value>, error: Option<serde_json::Value>) -> LuauTask {
+        LuauTask { path: "universes/1/places/2/luau-execution-sessions/3/tasks/4".to_string(), state: state.to_string(), output, error }
+    }
+
+    #[test]
+    fn test_task_outcome() {
+        let path = Path::new("scripts/migrate.luau");
+
+        let complete = task("COMPLETE", Some(serde_json::json!({ "results": [1, "done"] })), None);
+        assert_eq!(task_outcome(&complete, path).unwrap(), Some("[\n  1,\n  \"done\"\n]".to_string()));
+        // Nothing returned: nothing to print
+        assert_eq!(task_outcome(&task("COMPLETE", Some(serde_json::json!({ "results": [] })), None), path).unwrap(), None);
+        assert_eq!(task_outcome(&task("COMPLETE", None, None), path).unwrap(), None);
+
+        let failed = task("FAILED", None, Some(serde_json::json!({ "code": "SCRIPT_ERROR", "message": "attempt to index nil" })));
+        assert_eq!(task_outcome(&failed, path).unwrap_err().to_string(), "Script scripts/migrate.luau failed: attempt to index nil");
+        let cancelled = task("CANCELLED", None, None);
+        assert_eq!(task_outcome(&cancelled, path).unwrap_err().to_string(), "Script scripts/migrate.luau cancelled: no error message");
+    }
+}
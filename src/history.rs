@@ -0,0 +1,198 @@
+//! Snapshots of the synced state, for undoing a bad sync.
+//!
+//! Every successful `run` appends a snapshot of the resulting `SyncState` to
+//! `.rblxsync/history/` in the project root, together with the time, the git
+//! commit of the project (if it is a repository) and a hash of the config file.
+//! `rollback --to <snapshot>` pushes a snapshot's metadata back to Roblox.
+
+use crate::config::ConfigFormat;
+use crate::project::Project;
+use crate::state::{write_atomic, LockFile, StateScope, SyncState, STATE_VERSION};
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Directory holding the snapshots, relative to the project root
+pub const HISTORY_DIR: &str = ".rblxsync/history";
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Snapshot {
+    /// Lock file schema version of `state` (missing in snapshots taken before lock files had sections)
+    #[serde(default)]
+    pub version: u32,
+    /// Sortable identifier, e.g. `20261018T152944Z`; also the file name
+    pub id: String,
+    /// RFC 3339 timestamp of the run
+    pub created_at: String,
+    /// `HEAD` of the project's git repository at the time of the run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_commit: Option<String>,
+    /// SHA-256 of the config file that was synced
+    pub config_hash: String,
+    /// The command that produced the snapshot, e.g. "run" or "rollback"
+    pub command: String,
+    pub state: SyncState,
+}
+
+/// The snapshots of one universe (and environment); projects with several
+/// configs share the history directory
+pub struct History {
+    dir: PathBuf,
+    scope: StateScope,
+}
+
+impl History {
+    pub fn new(dir: PathBuf, scope: StateScope) -> Self {
+        Self { dir, scope }
+    }
+
+    pub fn for_project(project: &Project, scope: StateScope) -> Self {
+        Self::new(project.resolve(HISTORY_DIR), scope)
+    }
+
+    /// Append a snapshot of `state` as synced from the project's current config
+    pub fn record(&self, project: &Project, command: &str, state: &SyncState) -> Result<Snapshot> {
+        let config = fs::read(project.config_path())
+            .with_context(|| format!("Failed to read config file at {:?}", project.config_path()))?;
+        let now = chrono::Utc::now();
+
+        // Runs within the same second get a numeric suffix to keep ids unique and ordered
+        let base_id = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let mut id = base_id.clone();
+        let mut n = 1;
+        while self.path(&id).exists() {
+            id = format!("{}-{}", base_id, n);
+            n += 1;
+        }
+
+        let snapshot = Snapshot {
+            version: STATE_VERSION,
+            id,
+            created_at: now.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            git_commit: git_commit(project.root()),
+            config_hash: format!("{:x}", Sha256::digest(&config)),
+            command: command.to_string(),
+            state: state.clone(),
+        };
+        write_atomic(&self.path(&snapshot.id), ConfigFormat::Json.serialize(&snapshot)?.as_bytes())?;
+        Ok(snapshot)
+    }
+
+    /// All snapshots of this universe, oldest first. Snapshots from before lock
+    /// files had sections don't record their universe and are always included.
+    pub fn list(&self) -> Result<Vec<Snapshot>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read history at {:?}", self.dir)),
+        };
+
+        let mut snapshots = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                let snapshot = Self::read(&path)?;
+                if snapshot.state.scope().map_or(true, |scope| scope == self.scope) {
+                    snapshots.push(snapshot);
+                }
+            }
+        }
+        snapshots.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(snapshots)
+    }
+
+    /// Find a snapshot by id, unique id prefix, or `latest`/`previous`
+    pub fn find(&self, target: &str) -> Result<Snapshot> {
+        let mut snapshots = self.list()?;
+        let count = snapshots.len();
+        let index = match target {
+            "latest" => count.checked_sub(1),
+            "previous" => count.checked_sub(2),
+            _ => {
+                let matches: Vec<usize> = (0..count).filter(|&i| snapshots[i].id.starts_with(target)).collect();
+                match matches.as_slice() {
+                    [index] => Some(*index),
+                    [] => None,
+                    _ => return Err(anyhow!("Snapshot '{}' is ambiguous ({} matches)", target, matches.len())),
+                }
+            }
+        };
+        index
+            .map(|i| snapshots.swap_remove(i))
+            .ok_or_else(|| anyhow!("No snapshot '{}' in {}", target, self.dir.display()))
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+
+    /// Read a snapshot file, migrating the embedded state like a lock file
+    fn read(path: &Path) -> Result<Snapshot> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read snapshot {:?}", path))?;
+        let mut raw: serde_json::Value = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse snapshot {:?}", path))?;
+        let state = raw.get_mut("state").map(serde_json::Value::take).unwrap_or_default();
+
+        // Older snapshots embed a whole (single-universe) lock file, which carries its
+        // own version; newer ones a single section, wrapped here to migrate it the same way
+        let document = if state.get("version").is_some() {
+            state
+        } else {
+            serde_json::json!({ "version": raw["version"], "universes": { "snapshot": state } })
+        };
+        let (lock_file, _) = LockFile::parse(ConfigFormat::Json, &document.to_string())
+            .with_context(|| format!("Failed to parse state in snapshot {:?}", path))?;
+        let state: SyncState = lock_file.universes.into_values().next().unwrap_or_default();
+
+        raw["state"] = serde_json::to_value(&state)?;
+        raw["version"] = STATE_VERSION.into();
+        serde_json::from_value(raw).with_context(|| format!("Failed to parse snapshot {:?}", path))
+    }
+}
+
+/// Current commit of the git repository containing `dir`, if any
+fn git_commit(dir: &Path) -> Option<String> {
+    let output = std::process::Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .current_dir(dir)
+        .output()
+        .ok()?;
+    let commit = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    (output.status.success() && !commit.is_empty()).then_some(commit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_list_and_find() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("rblxsync.yml"), "universe:\n  id: 1\n").unwrap();
+        let project = Project::new(&dir.path().join("rblxsync.yml"), None);
+        let scope = StateScope::new(1, None);
+        let history = History::for_project(&project, scope.clone());
+
+        let mut state = SyncState::new(&scope);
+        state.update_developer_product(5, "Coins".to_string(), None, Some(100), None, None);
+        let first = history.record(&project, "run", &state).unwrap();
+        state.update_developer_product(5, "Coins".to_string(), None, Some(999), None, None);
+        let second = history.record(&project, "run", &state).unwrap();
+
+        let snapshots = history.list().unwrap();
+        assert_eq!(snapshots.iter().map(|s| s.id.as_str()).collect::<Vec<_>>(), [first.id.as_str(), second.id.as_str()]);
+        assert_eq!(first.config_hash, second.config_hash);
+
+        let previous = history.find("previous").unwrap();
+        assert_eq!(previous.state.developer_products[&5].price, Some(100));
+        assert_eq!(history.find(&second.id).unwrap().state.developer_products[&5].price, Some(999));
+        assert!(history.find("1999").is_err());
+
+        // Other universes sharing the directory have their own history
+        let other = History::for_project(&project, StateScope::new(2, None));
+        assert!(other.list().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,162 @@
+//! Leaderboards kept in Ordered DataStores (`ordered_data_stores:` in the config).
+//!
+//! `leaderboard export` writes the ranked entries to a file, and `leaderboard
+//! reset` starts a new season by deleting every entry, after exporting them to
+//! the store's `archive_dir`.
+
+use crate::api::RobloxClient;
+use crate::config::{ConfigFormat, OrderedDataStoreConfig, RblxSyncConfig};
+use crate::datastore::join_all;
+use crate::project::Project;
+use crate::state::write_atomic;
+use anyhow::{anyhow, Context, Result};
+use log::{debug, info};
+use serde::Serialize;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+/// An entry of the leaderboard, ranked from the highest value
+#[derive(Debug, Clone, Serialize)]
+pub struct LeaderboardEntry {
+    pub rank: usize,
+    pub id: String,
+    pub value: i64,
+}
+
+/// Export the ranked entries to `output` (format from its extension) or print them as JSON
+pub async fn export(config: &RblxSyncConfig, project: &Project, client: &RobloxClient, name: &str, scope: Option<&str>, output: Option<&Path>, limit: Option<usize>) -> Result<()> {
+    let store = find(config, name)?;
+    let scope = scope.unwrap_or(&store.scope);
+    let entries = fetch(client, config.universe.id, &store.name, scope, limit).await?;
+
+    match output {
+        Some(path) => {
+            let path = project.resolve(path);
+            let format = ConfigFormat::from_path(&path)?;
+            write_atomic(&path, format.serialize(&serde_json::json!({ "entries": entries }))?.as_bytes())?;
+            info!("Exported {} entries of {} ({}) to {}", entries.len(), store.name, scope, path.display());
+        }
+        None => print!("{}", ConfigFormat::Json.serialize(&entries)?),
+    }
+    Ok(())
+}
+
+/// Delete every entry of the leaderboard, exporting them to its `archive_dir` first
+#[allow(clippy::too_many_arguments)]
+pub async fn reset(config: &RblxSyncConfig, project: &Project, client: &RobloxClient, name: &str, scope: Option<&str>, archive: bool, concurrency: usize, dry_run: bool) -> Result<()> {
+    let store = find(config, name)?;
+    let scope = scope.unwrap_or(&store.scope).to_string();
+    let universe_id = config.universe.id;
+    let archive_path = match (&store.archive_dir, archive) {
+        (Some(dir), true) => {
+            let timestamp = chrono::Utc::now().format("%Y%m%dT%H%M%SZ");
+            Some(project.resolve(dir).join(format!("{}-{}-{}.json", store.name, scope, timestamp)))
+        }
+        (None, true) => {
+            return Err(anyhow!(
+                "{} has no archive_dir, so its entries would be deleted without an export. \
+                 Set archive_dir for it, or pass --no-archive to reset without one.",
+                store.name
+            ));
+        }
+        (_, false) => None,
+    };
+
+    let entries = fetch(client, universe_id, &store.name, &scope, None).await?;
+    if entries.is_empty() {
+        info!("{} ({}) has no entries; nothing to reset", store.name, scope);
+        return Ok(());
+    }
+
+    if dry_run {
+        info!("  [RESET] {} ({}) - would delete {} entries", store.name, scope, entries.len());
+        if let Some(path) = &archive_path {
+            info!("Dry Run: Would export the entries to {}", path.display());
+        }
+        return Ok(());
+    }
+
+    if let Some(path) = &archive_path {
+        std::fs::create_dir_all(path.parent().unwrap_or(Path::new(".")))
+            .with_context(|| format!("Failed to create archive directory for {:?}", path))?;
+        write_atomic(path, ConfigFormat::Json.serialize(&serde_json::json!({ "entries": entries }))?.as_bytes())?;
+        info!("Exported {} entries to {}", entries.len(), path.display());
+    }
+
+    let semaphore = Arc::new(Semaphore::new(concurrency.max(1)));
+    let mut tasks = JoinSet::new();
+    for entry in entries {
+        let (client, semaphore) = (client.clone(), semaphore.clone());
+        let (data_store, scope) = (store.name.clone(), scope.clone());
+        tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await?;
+            client.delete_ordered_data_store_entry(universe_id, &data_store, &scope, &entry.id).await
+                .with_context(|| format!("{}/{}/{}", data_store, scope, entry.id))?;
+            debug!("  [DELETED] {}/{}/{}", data_store, scope, entry.id);
+            Ok(true)
+        });
+    }
+
+    let (deleted, failed) = join_all(tasks).await;
+    info!("Leaderboard Reset Summary: {} deleted, {} failed", deleted, failed);
+    if failed > 0 {
+        return Err(anyhow!("{} entries of {} could not be deleted; run the reset again to retry them", failed, store.name));
+    }
+    Ok(())
+}
+
+fn find<'a>(config: &'a RblxSyncConfig, name: &str) -> Result<&'a OrderedDataStoreConfig> {
+    config.ordered_data_stores.iter()
+        .find(|store| store.name == name)
+        .ok_or_else(|| {
+            let names: Vec<&str> = config.ordered_data_stores.iter().map(|s| s.name.as_str()).collect();
+            anyhow!("No ordered DataStore '{}' in ordered_data_stores (configured: {})", name,
+                if names.is_empty() { "none".to_string() } else { names.join(", ") })
+        })
+}
+
+/// Entries from the highest value down, at most `limit` of them
+async fn fetch(client: &RobloxClient, universe_id: u64, data_store: &str, scope: &str, limit: Option<usize>) -> Result<Vec<LeaderboardEntry>> {
+    let mut entries = Vec::new();
+    let mut page_token = None;
+    loop {
+        let page = client.list_ordered_data_store_entries(universe_id, data_store, scope, true, page_token).await?;
+        for item in page.data {
+            let Some(id) = item["id"].as_str() else { continue };
+            // int64 values may come back as JSON strings
+            let value = item["value"].as_i64()
+                .or_else(|| item["value"].as_str().and_then(|v| v.parse().ok()))
+                .unwrap_or_default();
+            entries.push(LeaderboardEntry { rank: entries.len() + 1, id: id.to_string(), value });
+            if limit.is_some_and(|limit| entries.len() >= limit) {
+                return Ok(entries);
+            }
+        }
+        match page.next_page_cursor {
+            Some(next) if !next.is_empty() => page_token = Some(next),
+            _ => return Ok(entries),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_configured_store() {
+        let config = RblxSyncConfig::parse(ConfigFormat::Yaml, r#"
+universe:
+  id: 1
+ordered_data_stores:
+  - name: "Season5Wins"
+    archive_dir: "archive"
+"#).unwrap();
+        let store = find(&config, "Season5Wins").unwrap();
+        assert_eq!(store.scope, "global");
+        let err = find(&config, "Season4Wins").unwrap_err();
+        assert!(err.to_string().contains("configured: Season5Wins"));
+    }
+}
@@ -1,5 +1,13 @@
 pub mod api;
+pub mod backend;
 pub mod config;
+pub mod datastore;
+pub mod exec;
+pub mod history;
+pub mod leaderboard;
+pub mod lock;
 pub mod state;
 pub mod commands;
 pub mod output;
+pub mod place;
+pub mod project;
@@ -0,0 +1,345 @@
+//! Advisory locking for the lock file.
+//!
+//! A `<lock file>.lock` marker is created exclusively while a command that
+//! mutates state (and Roblox) is running, so two CI jobs or a developer and CI
+//! cannot interleave their reads/writes of `rblxsync-lock.yml` or race to create
+//! the same resources. The marker records who holds it so stale locks left by
+//! crashed runs can be detected and cleared.
+
+use anyhow::{anyhow, Context, Result};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Locks older than this are considered abandoned regardless of their holder
+const STALE_LOCK_AGE: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// How often to retry while waiting for another run to release the lock
+pub(crate) const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Information about the process holding a lock
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct LockInfo {
+    pub pid: u32,
+    pub host: String,
+    /// Unix timestamp (seconds) when the lock was acquired
+    pub acquired_at: u64,
+    /// The command that acquired the lock, e.g. "run"
+    pub command: String,
+}
+
+impl LockInfo {
+    pub(crate) fn current(command: &str) -> Self {
+        Self {
+            pid: std::process::id(),
+            host: hostname(),
+            acquired_at: unix_now(),
+            command: command.to_string(),
+        }
+    }
+
+    /// Placeholder for a lock whose holder cannot be read, held since `acquired_at`
+    pub(crate) fn unknown(acquired_at: u64) -> Self {
+        Self {
+            pid: 0,
+            host: "unknown".to_string(),
+            acquired_at,
+            command: "unknown".to_string(),
+        }
+    }
+
+    fn age(&self) -> Duration {
+        Duration::from_secs(unix_now().saturating_sub(self.acquired_at))
+    }
+
+    /// Whether the holder is known to be gone: a dead process on this host,
+    /// or a lock older than `STALE_LOCK_AGE`.
+    pub(crate) fn is_stale(&self) -> bool {
+        if self.age() > STALE_LOCK_AGE {
+            return true;
+        }
+        self.pid != 0 && self.host == hostname() && process_exited(self.pid)
+    }
+
+    pub(crate) fn describe(&self) -> String {
+        format!("`{}` (PID {} on {}, acquired {}s ago)", self.command, self.pid, self.host, self.age().as_secs())
+    }
+}
+
+/// Guard for an acquired state lock; the marker file is removed on drop
+#[derive(Debug)]
+pub struct StateLock {
+    path: PathBuf,
+    info: LockInfo,
+}
+
+impl StateLock {
+    /// Path of the lock marker for a given lock file
+    pub fn marker_path(state_path: &Path) -> PathBuf {
+        let mut name = state_path.file_name().unwrap_or_default().to_os_string();
+        name.push(".lock");
+        state_path.with_file_name(name)
+    }
+
+    /// Acquire the lock for `state_path`, waiting up to `timeout` for another
+    /// run to release it. Stale locks are removed automatically.
+    pub async fn acquire(state_path: &Path, command: &str, timeout: Duration) -> Result<Self> {
+        let path = Self::marker_path(state_path);
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+
+        let info = LockInfo::current(command);
+        let started = Instant::now();
+        let mut announced = false;
+
+        loop {
+            if create_marker(&path, &info)? {
+                return Ok(Self { path, info });
+            }
+
+            match read_marker(&path)? {
+                Some(holder) if holder.is_stale() => {
+                    warn!("Removing stale lock held by {}", holder.describe());
+                    remove_stale(&path, &holder)?;
+                    continue;
+                }
+                Some(holder) => {
+                    if started.elapsed() >= timeout {
+                        return Err(anyhow!(
+                            "Lock file {:?} is locked by {}. Wait for it to finish, pass --lock-timeout to wait longer, \
+                             or run `rblxsync force-unlock` if that run is no longer active.",
+                            state_path, holder.describe()
+                        ));
+                    }
+                    if !announced {
+                        info!("Waiting for lock held by {}...", holder.describe());
+                        announced = true;
+                    }
+                }
+                // Released between our create attempt and the read; retry immediately
+                None => continue,
+            }
+
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    /// Read the current holder of the lock for `state_path`, if any.
+    pub fn holder(state_path: &Path) -> Result<Option<LockInfo>> {
+        read_marker(&Self::marker_path(state_path))
+    }
+
+    /// Remove the lock for `state_path` regardless of who holds it.
+    /// Returns the previous holder, if there was one.
+    pub fn force_unlock(state_path: &Path) -> Result<Option<LockInfo>> {
+        let holder = Self::holder(state_path)?;
+        remove_if_exists(&Self::marker_path(state_path))?;
+        Ok(holder)
+    }
+}
+
+impl Drop for StateLock {
+    fn drop(&mut self) {
+        // Only remove the marker if it is still ours (it may have been force-unlocked
+        // and re-acquired by another run in the meantime)
+        let still_ours = fs::read_to_string(&self.path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<LockInfo>(&content).ok())
+            .is_some_and(|holder| holder == self.info);
+        if still_ours {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Parse a lock timeout such as `30`, `30s`, `5m` or `1h` (plain numbers are seconds)
+pub fn parse_timeout(value: &str) -> Result<Duration> {
+    let value = value.trim();
+    let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(index) => value.split_at(index),
+        None => (value, "s"),
+    };
+    let number: u64 = number.parse().map_err(|_| anyhow!("invalid timeout '{}'", value))?;
+    let seconds = match unit {
+        "s" => number,
+        "m" => number * 60,
+        "h" => number * 60 * 60,
+        _ => return Err(anyhow!("invalid timeout unit in '{}' (use s, m or h)", value)),
+    };
+    Ok(Duration::from_secs(seconds))
+}
+
+/// Create the marker holding `info`, unless one already exists. The marker is
+/// written to a temporary file first and then hard-linked into place, so other
+/// runs never see it empty or half-written.
+fn create_marker(path: &Path, info: &LockInfo) -> Result<bool> {
+    let temp = unique_sibling(path, "tmp");
+    let written = fs::File::create(&temp).and_then(|mut file| {
+        file.write_all(serde_json::to_string(info)?.as_bytes())?;
+        file.sync_all()
+    });
+    let linked = written.and_then(|_| fs::hard_link(&temp, path));
+    let _ = fs::remove_file(&temp);
+    match linked {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => Ok(false),
+        Err(e) => Err(e).with_context(|| format!("Failed to create lock {:?}", path)),
+    }
+}
+
+/// Read the holder recorded in a marker. A marker that cannot be parsed (e.g. a
+/// corrupted file) is treated as held since its last modification, so it only
+/// becomes stale once it is older than `STALE_LOCK_AGE`.
+fn read_marker(path: &Path) -> Result<Option<LockInfo>> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read lock {:?}", path)),
+    };
+    if let Ok(info) = serde_json::from_str(&content) {
+        return Ok(Some(info));
+    }
+    let modified = fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or_else(unix_now);
+    Ok(Some(LockInfo::unknown(modified)))
+}
+
+/// Remove a stale marker, but only if it still records `expected`. The marker is
+/// first moved to a unique name, so a lock another run acquired after we read
+/// the stale one is put back instead of deleted.
+fn remove_stale(path: &Path, expected: &LockInfo) -> Result<()> {
+    let claimed = unique_sibling(path, "stale");
+    match fs::rename(path, &claimed) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to remove lock {:?}", path)),
+    }
+    if read_marker(&claimed)?.as_ref() != Some(expected) {
+        // Not the lock we inspected; restore it unless yet another run has locked since
+        match fs::hard_link(&claimed, path) {
+            Err(e) if e.kind() != std::io::ErrorKind::AlreadyExists => {
+                return Err(e).with_context(|| format!("Failed to restore lock {:?}", path));
+            }
+            _ => {}
+        }
+    }
+    remove_if_exists(&claimed)
+}
+
+/// A path next to `path` that no other run or call will use
+fn unique_sibling(path: &Path, tag: &str) -> PathBuf {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or_default();
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}.{}.{}.{}", tag, std::process::id(), nanos, COUNTER.fetch_add(1, Ordering::Relaxed)));
+    path.with_file_name(name)
+}
+
+fn remove_if_exists(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(e).with_context(|| format!("Failed to remove lock {:?}", path))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
+
+fn hostname() -> String {
+    std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .ok()
+        .or_else(|| fs::read_to_string("/proc/sys/kernel/hostname").ok())
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .map(|h| h.trim().to_string())
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Whether a process on this host has exited. Only detectable where `/proc`
+/// exists; elsewhere the process is assumed alive and the age check applies.
+fn process_exited(pid: u32) -> bool {
+    let proc_root = Path::new("/proc");
+    proc_root.join("self").exists() && !proc_root.join(pid.to_string()).exists()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A lock file path in a fresh directory, removed when the `TempDir` is dropped
+    fn temp_state_path() -> (tempfile::TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rblxsync-lock.yml");
+        (dir, path)
+    }
+
+    #[tokio::test]
+    async fn test_lock_is_exclusive_and_released_on_drop() {
+        let (_dir, state_path) = temp_state_path();
+        let lock = StateLock::acquire(&state_path, "run", Duration::ZERO).await.unwrap();
+        assert!(StateLock::marker_path(&state_path).exists());
+
+        let err = StateLock::acquire(&state_path, "run", Duration::ZERO).await.unwrap_err();
+        assert!(err.to_string().contains("is locked by `run`"));
+
+        drop(lock);
+        assert!(!StateLock::marker_path(&state_path).exists());
+    }
+
+    #[tokio::test]
+    async fn test_stale_lock_is_replaced() {
+        let (_dir, state_path) = temp_state_path();
+        let old = LockInfo { pid: 1, host: "ci-runner".to_string(), acquired_at: 0, command: "run".to_string() };
+        fs::write(StateLock::marker_path(&state_path), serde_json::to_string(&old).unwrap()).unwrap();
+
+        let lock = StateLock::acquire(&state_path, "run", Duration::ZERO).await.unwrap();
+        assert_eq!(StateLock::holder(&state_path).unwrap(), Some(lock.info.clone()));
+    }
+
+    #[test]
+    fn test_unparseable_and_replaced_markers_are_kept() {
+        let (_dir, state_path) = temp_state_path();
+        let marker = StateLock::marker_path(&state_path);
+
+        // A fresh marker that can't be parsed is still held
+        fs::write(&marker, "").unwrap();
+        let holder = StateLock::holder(&state_path).unwrap().unwrap();
+        assert!(!holder.is_stale());
+
+        // A stale holder that was replaced by a live lock in the meantime is not removed
+        let stale = LockInfo { pid: 1, host: "ci-runner".to_string(), acquired_at: 0, command: "run".to_string() };
+        let live = LockInfo::current("run");
+        fs::write(&marker, serde_json::to_string(&live).unwrap()).unwrap();
+        remove_stale(&marker, &stale).unwrap();
+        assert_eq!(StateLock::holder(&state_path).unwrap(), Some(live));
+
+        remove_stale(&marker, &StateLock::holder(&state_path).unwrap().unwrap()).unwrap();
+        assert!(!marker.exists());
+        assert_eq!(fs::read_dir(state_path.parent().unwrap()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_parse_timeout() {
+        assert_eq!(parse_timeout("30").unwrap(), Duration::from_secs(30));
+        assert_eq!(parse_timeout("45s").unwrap(), Duration::from_secs(45));
+        assert_eq!(parse_timeout("5m").unwrap(), Duration::from_secs(300));
+        assert_eq!(parse_timeout("1h").unwrap(), Duration::from_secs(3600));
+        assert!(parse_timeout("5d").is_err());
+        assert!(parse_timeout("soon").is_err());
+    }
+}
@@ -1,10 +1,20 @@
 use clap::{Parser, Subcommand};
-use rblxsync::config::{Config, RblxSyncConfig};
+use rblxsync::config::{Config, ConfigFormat, RblxSyncConfig};
 use rblxsync::api::{RobloxClient, RobloxCookieClient};
-use rblxsync::state::SyncState;
+use rblxsync::backend;
+use rblxsync::datastore::{self, EntryFilter, TransferOptions};
+use rblxsync::project::Project;
+use rblxsync::exec;
+use rblxsync::history::History;
+use rblxsync::leaderboard;
+use rblxsync::place;
+use rblxsync::lock::{self, StateLock};
+use rblxsync::state::ResourceKind;
 use rblxsync::commands;
 use log::{info, error};
-use std::path::Path;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 #[derive(Parser)]
 #[command(name = "rblxsync")]
@@ -13,9 +23,18 @@ struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
 
-    /// Path to config file
-    #[arg(short, long, default_value = "rblxsync.yml")]
-    config: String,
+    /// Path to config file (.yml, .yaml, .toml or .json). Defaults to the first
+    /// rblxsync.{yml,yaml,toml,json} found in the current directory
+    #[arg(short, long)]
+    config: Option<String>,
+
+    /// Path to lock file (defaults to rblxsync-lock.yml, .toml or .json to match the config file, next to it; an existing lock file in another format is reused)
+    #[arg(long)]
+    lock_file: Option<String>,
+
+    /// How long to wait for another run holding the lock file, e.g. 30s or 5m
+    #[arg(long, default_value = "0", value_parser = lock::parse_timeout)]
+    lock_timeout: Duration,
 }
 
 #[derive(Subcommand)]
@@ -27,7 +46,33 @@ enum Commands {
         dry_run: bool,
     },
     /// Publish place files
-    Publish,
+    Publish {
+        /// Restart the universe's servers once every place has been published
+        #[arg(long)]
+        restart: bool,
+        /// Only restart servers running an outdated version
+        #[arg(long)]
+        outdated_only: bool,
+    },
+    /// List or roll back place versions
+    Place {
+        #[command(subcommand)]
+        command: PlaceCommands,
+    },
+    /// Run a Luau script in a server of a place and print its output
+    Exec {
+        /// Luau script file
+        script: PathBuf,
+        /// Place ID (defaults to the only place in the config)
+        #[arg(long)]
+        place: Option<u64>,
+        /// Place version to run against (defaults to the latest)
+        #[arg(long)]
+        version: Option<u64>,
+        /// How long the script may run, e.g. 60s or 5m (default: 5m)
+        #[arg(long, value_parser = lock::parse_timeout)]
+        timeout: Option<Duration>,
+    },
     /// Validate configuration file
     Validate,
     /// Export existing resources to Luau/Lua
@@ -39,6 +84,285 @@ enum Commands {
         #[arg(long)]
         lua: bool,
     },
+    /// Inspect the configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
+    /// Remove the lock held on the lock file by another (crashed) run
+    ForceUnlock,
+    /// List the state snapshots recorded after each successful run
+    History,
+    /// Revert universe settings and resource metadata to a recorded snapshot
+    Rollback {
+        /// Snapshot ID (or a unique prefix of it), `latest` or `previous`
+        #[arg(long)]
+        to: String,
+        /// Preview changes without applying them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Back up or restore DataStore entries
+    Datastore {
+        #[command(subcommand)]
+        command: DatastoreCommands,
+    },
+    /// Export or reset leaderboards kept in Ordered DataStores
+    Leaderboard {
+        #[command(subcommand)]
+        command: LeaderboardCommands,
+    },
+    /// Send MessagingService messages to live servers
+    Message {
+        #[command(subcommand)]
+        command: MessageCommands,
+    },
+    /// Inspect or edit the tracked state (lock file)
+    State {
+        #[command(subcommand)]
+        command: StateCommands,
+    },
+    /// Create a new rblxsync project (config, assets directory, .env template)
+    Init {
+        /// Universe ID (prompted for if omitted in a terminal)
+        #[arg(long)]
+        universe: Option<u64>,
+        /// Creator user or group ID used for icon uploads
+        #[arg(long)]
+        creator_id: Option<String>,
+        /// Creator type: user or group
+        #[arg(long)]
+        creator_type: Option<String>,
+        /// Config format to write: yaml, toml or json (defaults to the --config extension)
+        #[arg(long)]
+        format: Option<ConfigFormat>,
+        /// Don't query Roblox for the universe's existing name and resources
+        #[arg(long)]
+        no_fetch: bool,
+        /// Never prompt; fail if required values are missing
+        #[arg(long)]
+        non_interactive: bool,
+        /// Overwrite an existing config file
+        #[arg(long)]
+        force: bool,
+    },
+    /// Import existing resources from a universe into a new config and lock file
+    Import {
+        /// Universe ID to import from
+        #[arg(long)]
+        universe: u64,
+        /// Config format to write: yaml, toml or json (defaults to the --config extension)
+        #[arg(long)]
+        format: Option<ConfigFormat>,
+        /// Overwrite an existing config file
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+/// Exit if `--format` contradicts the extension of an explicit `--config` path
+fn check_format_matches(format: Option<ConfigFormat>, config: Option<&str>) -> anyhow::Result<()> {
+    if let (Some(format), Some(path)) = (format, config) {
+        if ConfigFormat::from_path(Path::new(path))? != format {
+            error!("--format {} does not match the extension of {}", format.extension(), path);
+            std::process::exit(1);
+        }
+    }
+    Ok(())
+}
+
+#[derive(Subcommand)]
+enum ConfigCommands {
+    /// Print the config file
+    Show {
+        /// Print the config after expanding generate blocks, defaults and templates
+        #[arg(long)]
+        expanded: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum StateCommands {
+    /// List every tracked resource
+    List,
+    /// Show the recorded state of a resource
+    Show {
+        /// Resource type: game_pass, developer_product, badge or subscription
+        kind: ResourceKind,
+        /// Name or Roblox ID of the resource
+        target: String,
+    },
+    /// Stop tracking a resource (it is not deleted on Roblox)
+    Rm {
+        /// Resource type: game_pass, developer_product, badge or subscription
+        kind: ResourceKind,
+        /// Name or Roblox ID of the resource
+        target: String,
+    },
+    /// Relink a tracked resource to a different existing Roblox ID
+    Mv {
+        /// Resource type: game_pass, developer_product, badge or subscription
+        kind: ResourceKind,
+        /// Name or current Roblox ID of the resource
+        target: String,
+        /// Roblox ID to link it to
+        id: u64,
+    },
+    /// Start tracking an existing Roblox resource under a config entry name
+    Import {
+        /// Resource type: game_pass, developer_product, badge or subscription
+        kind: ResourceKind,
+        /// Name of the entry in the config
+        name: String,
+        /// Roblox ID of the existing resource
+        id: u64,
+    },
+    /// Assign the state of a lock file written before it recorded universes to this config's universe
+    Claim,
+    /// Three-way merge of lock files (git merge driver: `rblxsync state merge %O %A %B`)
+    Merge {
+        /// Common ancestor
+        base: PathBuf,
+        /// Our version; the merged result is written here
+        ours: PathBuf,
+        /// Their version
+        theirs: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum DatastoreCommands {
+    /// Download DataStore entries into a backup directory
+    Backup {
+        /// Directory to write the backup to
+        #[arg(short, long)]
+        output: PathBuf,
+        #[command(flatten)]
+        options: DatastoreOptions,
+    },
+    /// Write the entries of a backup directory back to the DataStores
+    Restore {
+        /// Backup directory to restore from
+        #[arg(short, long)]
+        input: PathBuf,
+        /// Restore a backup taken from a different universe
+        #[arg(long)]
+        allow_other_universe: bool,
+        #[command(flatten)]
+        options: DatastoreOptions,
+    },
+}
+
+#[derive(Subcommand)]
+enum PlaceCommands {
+    /// List the versions of a place, marking those published by rblxsync
+    Versions {
+        /// Place ID
+        place_id: u64,
+    },
+    /// Publish an earlier version of a place again
+    Rollback {
+        /// Place ID
+        place_id: u64,
+        /// Version number to restore
+        #[arg(long)]
+        to: u64,
+        /// Place file of that version (defaults to the copy kept when rblxsync published it)
+        #[arg(long)]
+        file: Option<PathBuf>,
+        /// Show what would be published without publishing it
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum MessageCommands {
+    /// Publish a JSON message to a topic
+    Publish {
+        /// MessagingService topic
+        topic: String,
+        /// Message as JSON (at most 1 kB)
+        message: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum LeaderboardCommands {
+    /// Write the ranked entries to a file (or print them as JSON)
+    Export {
+        /// Name of the Ordered DataStore in `ordered_data_stores`
+        name: String,
+        /// Output file (.json, .yml or .toml)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Scope (defaults to the one in the config)
+        #[arg(long)]
+        scope: Option<String>,
+        /// Only the top N entries
+        #[arg(long)]
+        limit: Option<usize>,
+    },
+    /// Delete every entry, exporting them to the store's archive_dir first
+    Reset {
+        /// Name of the Ordered DataStore in `ordered_data_stores`
+        name: String,
+        /// Scope (defaults to the one in the config)
+        #[arg(long)]
+        scope: Option<String>,
+        /// Don't export the entries before deleting them
+        #[arg(long)]
+        no_archive: bool,
+        /// Maximum number of entries deleted at the same time
+        #[arg(long, default_value_t = 8)]
+        concurrency: usize,
+        /// Show what would be deleted without deleting anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(clap::Args)]
+struct DatastoreOptions {
+    /// Universe ID (defaults to the one in the config)
+    #[arg(long)]
+    universe: Option<u64>,
+    /// Only this DataStore (repeatable; defaults to all)
+    #[arg(long = "store")]
+    stores: Vec<String>,
+    /// Only entries in this scope
+    #[arg(long)]
+    scope: Option<String>,
+    /// Only keys starting with this prefix
+    #[arg(long)]
+    prefix: Option<String>,
+    /// Maximum number of entries transferred at the same time
+    #[arg(long, default_value_t = 8)]
+    concurrency: usize,
+    /// Continue an interrupted run, skipping entries it already transferred
+    #[arg(long)]
+    resume: bool,
+    /// List the entries that would be transferred without transferring them
+    #[arg(long)]
+    dry_run: bool,
+}
+
+impl DatastoreOptions {
+    fn filter(&self) -> EntryFilter {
+        EntryFilter { stores: self.stores.clone(), scope: self.scope.clone(), prefix: self.prefix.clone() }
+    }
+
+    fn transfer(&self) -> TransferOptions {
+        TransferOptions { concurrency: self.concurrency, resume: self.resume, dry_run: self.dry_run }
+    }
+
+    /// The universe given on the command line, or else the config's
+    fn universe_id(&self, project: &Project) -> anyhow::Result<u64> {
+        match self.universe {
+            Some(id) => Ok(id),
+            None => Ok(RblxSyncConfig::load(project.config_path())?.universe.id),
+        }
+    }
 }
 
 #[tokio::main]
@@ -52,12 +376,22 @@ async fn main() -> anyhow::Result<()> {
     let env_config = Config::from_env(); 
 
     let command = args.command.unwrap_or(Commands::Run { dry_run: false });
+    let config_path = match (&args.config, &command) {
+        (Some(path), _) => PathBuf::from(path),
+        // Init and import write a new config, so the file name follows the requested format
+        (None, Commands::Import { format: Some(format), .. } | Commands::Init { format: Some(format), .. }) => {
+            PathBuf::from(format!("rblxsync.{}", format.extension()))
+        }
+        (None, _) => RblxSyncConfig::discover(Path::new(".")).unwrap_or_else(|| PathBuf::from("rblxsync.yml")),
+    };
+    let project = Project::new(&config_path, args.lock_file.as_deref().map(Path::new));
 
+    // Commands that don't require an API key
     match command {
         Commands::Validate => {
-            let path = Path::new(&args.config);
+            let path = project.config_path();
             if !path.exists() {
-                error!("Config file not found: {}", args.config);
+                error!("Config file not found: {}", path.display());
                 std::process::exit(1);
             }
             match RblxSyncConfig::load(path) {
@@ -76,6 +410,98 @@ async fn main() -> anyhow::Result<()> {
             }
             return Ok(());
         }
+        Commands::Config { command: ConfigCommands::Show { expanded } } => {
+            commands::show_config(&project, expanded)?;
+            return Ok(());
+        }
+        Commands::ForceUnlock => {
+            // Remote backends keep their lock next to the state, so they need the config
+            let holder = if project.config_path().exists() {
+                let config = RblxSyncConfig::load(project.config_path())?;
+                backend::from_config(&config, &project)?.force_unlock().await?
+            } else {
+                StateLock::force_unlock(project.lock_file())?
+            };
+            match holder {
+                Some(holder) => info!(
+                    "Removed lock held by `{}` (PID {} on {})", holder.command, holder.pid, holder.host
+                ),
+                None => info!("{} is not locked.", project.lock_file().display()),
+            }
+            return Ok(());
+        }
+        Commands::History => {
+            let config = RblxSyncConfig::load(project.config_path())?;
+            commands::history(&config, &project)?;
+            return Ok(());
+        }
+        Commands::State { command: StateCommands::Merge { base, ours, theirs } } => {
+            if !commands::state_merge(&base, &ours, &theirs, &project)? {
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        Commands::State { command } => {
+            let config = RblxSyncConfig::load(project.config_path())?;
+            let mut backend = backend::from_config(&config, &project)?;
+            // Only mv and import talk to Roblox, to check that the target ID exists
+            let client = match (&command, &env_config) {
+                (StateCommands::Mv { .. } | StateCommands::Import { .. }, Ok(c)) => Some(RobloxClient::new(c.api_key.clone())),
+                (StateCommands::Mv { .. } | StateCommands::Import { .. }, Err(e)) => {
+                    error!("Failed to load environment: {}", e);
+                    error!("Ensure ROBLOX_API_KEY is set.");
+                    std::process::exit(1);
+                }
+                _ => None,
+            };
+            let read_only = matches!(command, StateCommands::List | StateCommands::Show { .. });
+            let _lock = if read_only { None } else { Some(backend.lock("state", args.lock_timeout).await?) };
+            if let StateCommands::Claim = command {
+                backend.claim().await?;
+                info!("Assigned the unclaimed lock file state to {}", config.state_scope());
+                return Ok(());
+            }
+            let mut state = backend.load().await?;
+            let universe_id = config.universe.id;
+
+            match command {
+                StateCommands::List => commands::state_list(&state),
+                StateCommands::Show { kind, target } => commands::state_show(&state, kind, &target)?,
+                StateCommands::Rm { kind, target } => commands::state_rm(&mut state, kind, &target)?,
+                StateCommands::Mv { kind, target, id } => {
+                    commands::state_mv(&mut state, universe_id, kind, &target, id, client.as_ref().unwrap()).await?
+                }
+                StateCommands::Import { kind, name, id } => {
+                    commands::state_import(&mut state, universe_id, kind, &name, id, client.as_ref().unwrap()).await?
+                }
+                StateCommands::Claim | StateCommands::Merge { .. } => unreachable!(), // Handled above
+            }
+            if !read_only {
+                backend.save(&state).await?;
+            }
+            return Ok(());
+        }
+        Commands::Init { universe, creator_id, creator_type, format, no_fetch, non_interactive, force } => {
+            check_format_matches(format, args.config.as_deref())?;
+            // Pre-fill from Roblox only when an API key is available
+            let client = match (&env_config, no_fetch) {
+                (Ok(c), false) => Some(RobloxClient::new(c.api_key.clone())),
+                (Err(_), false) => {
+                    info!("ROBLOX_API_KEY not set, creating an empty config.");
+                    None
+                }
+                (_, true) => None,
+            };
+            let options = commands::InitOptions {
+                universe_id: universe,
+                creator_id,
+                creator_type,
+                interactive: !non_interactive && std::io::stdin().is_terminal(),
+                force,
+            };
+            commands::init(options, &project, client).await?;
+            return Ok(());
+        }
         _ => {}
     }
 
@@ -96,10 +522,12 @@ async fn main() -> anyhow::Result<()> {
             if dry_run {
                 info!("Dry-run mode enabled.");
             }
-            let config_path = Path::new(&args.config);
-            let config = RblxSyncConfig::load(config_path)?;
-            let root = config_path.parent().unwrap_or(Path::new("."));
-            let state = SyncState::load(root)?;
+            let config = RblxSyncConfig::load(project.config_path())?;
+            let mut backend = backend::from_config(&config, &project)?;
+            // Dry runs only read the (atomically written) state, so they don't block on other runs
+            let _lock = if dry_run { None } else { Some(backend.lock("run", args.lock_timeout).await?) };
+            info!("Using state from {}", backend.describe());
+            let state = backend.load().await?;
             
             // Check if universe settings are defined and require ROBLOX_COOKIE
             let cookie_client = if config.universe.has_settings() {
@@ -109,7 +537,7 @@ async fn main() -> anyhow::Result<()> {
                         Some(RobloxCookieClient::new(cookie.clone()))
                     }
                     None => {
-                        error!("Universe settings are defined in {} but ROBLOX_COOKIE is not set.", args.config);
+                        error!("Universe settings are defined in {} but ROBLOX_COOKIE is not set.", project.config_path().display());
                         error!("");
                         error!("To update universe settings (name, description, etc.), you must provide your");
                         error!(".ROBLOSECURITY cookie. Add the following to your .env file:");
@@ -129,17 +557,80 @@ async fn main() -> anyhow::Result<()> {
                 None
             };
             
-            commands::run(config, state, client, cookie_client, dry_run).await?;
+            commands::run(config, &project, state, backend.as_mut(), client, cookie_client, dry_run).await?;
+        }
+        Commands::Rollback { to, dry_run } => {
+            let config = RblxSyncConfig::load(project.config_path())?;
+            let snapshot = History::for_project(&project, config.state_scope()).find(&to)?;
+            let mut backend = backend::from_config(&config, &project)?;
+            let _lock = if dry_run { None } else { Some(backend.lock("rollback", args.lock_timeout).await?) };
+            let state = backend.load().await?;
+            let cookie_client = env_config.roblox_cookie.clone().map(RobloxCookieClient::new);
+            commands::rollback(&config, &project, state, backend.as_mut(), &snapshot, &client, cookie_client.as_ref(), dry_run).await?;
         }
-        Commands::Publish => {
-            let config = RblxSyncConfig::load(Path::new(&args.config))?;
-            commands::publish(config, client).await?;
+        Commands::Publish { restart, outdated_only } => {
+            let config = RblxSyncConfig::load(project.config_path())?;
+            let mut backend = backend::from_config(&config, &project)?;
+            let _lock = backend.lock("publish", args.lock_timeout).await?;
+            let mut state = backend.load().await?;
+            let restart = commands::RestartOptions { restart: restart || outdated_only, outdated_only };
+            commands::publish(config, &project, &mut state, backend.as_mut(), client, restart).await?;
+        }
+        Commands::Place { command: PlaceCommands::Versions { place_id } } => {
+            let config = RblxSyncConfig::load(project.config_path())?;
+            let mut backend = backend::from_config(&config, &project)?;
+            let state = backend.load().await?;
+            place::versions(&project, &state, place_id, &client).await?;
+        }
+        Commands::Place { command: PlaceCommands::Rollback { place_id, to, file, dry_run } } => {
+            let config = RblxSyncConfig::load(project.config_path())?;
+            let mut backend = backend::from_config(&config, &project)?;
+            let _lock = if dry_run { None } else { Some(backend.lock("place rollback", args.lock_timeout).await?) };
+            let mut state = backend.load().await?;
+            place::rollback(config.universe.id, place_id, to, file.as_deref(), &project, &mut state, backend.as_mut(), &client, dry_run).await?;
         }
         Commands::Export { output, lua } => {
-            let config = RblxSyncConfig::load(Path::new(&args.config))?;
+            let config = RblxSyncConfig::load(project.config_path())?;
             commands::export(config, client, output, lua).await?;
         }
-        Commands::Validate => unreachable!(), // Handled above
+        Commands::Datastore { command: DatastoreCommands::Backup { output, options } } => {
+            let universe_id = options.universe_id(&project)?;
+            datastore::backup(&client, universe_id, &output, &options.filter(), options.transfer()).await?;
+        }
+        Commands::Datastore { command: DatastoreCommands::Restore { input, allow_other_universe, options } } => {
+            let universe_id = options.universe_id(&project)?;
+            datastore::restore(&client, universe_id, &input, allow_other_universe, &options.filter(), options.transfer()).await?;
+        }
+        Commands::Exec { script, place, version, timeout } => {
+            let config = RblxSyncConfig::load(project.config_path())?;
+            let place_id = match (place, config.places.as_slice()) {
+                (Some(id), _) => id,
+                (None, [only]) => only.place_id,
+                (None, _) => {
+                    error!("Pass --place: the config does not have exactly one place");
+                    std::process::exit(1);
+                }
+            };
+            exec::run_script(&client, config.universe.id, place_id, version, &script, timeout).await?;
+        }
+        Commands::Message { command: MessageCommands::Publish { topic, message } } => {
+            let config = RblxSyncConfig::load(project.config_path())?;
+            commands::message_publish(config.universe.id, &topic, &message, &client).await?;
+        }
+        Commands::Leaderboard { command: LeaderboardCommands::Export { name, output, scope, limit } } => {
+            let config = RblxSyncConfig::load(project.config_path())?;
+            leaderboard::export(&config, &project, &client, &name, scope.as_deref(), output.as_deref(), limit).await?;
+        }
+        Commands::Leaderboard { command: LeaderboardCommands::Reset { name, scope, no_archive, concurrency, dry_run } } => {
+            let config = RblxSyncConfig::load(project.config_path())?;
+            leaderboard::reset(&config, &project, &client, &name, scope.as_deref(), !no_archive, concurrency, dry_run).await?;
+        }
+        Commands::Import { universe, format, force } => {
+            check_format_matches(format, args.config.as_deref())?;
+            let _lock = StateLock::acquire(project.lock_file(), "import", args.lock_timeout).await?;
+            commands::import(universe, &project, client, force).await?;
+        }
+        Commands::Validate | Commands::Config { .. } | Commands::ForceUnlock | Commands::History | Commands::State { .. } | Commands::Init { .. } => unreachable!(), // Handled above
     }
 
     Ok(())
@@ -14,8 +14,8 @@ use std::path::Path;
 /// * `state` - The sync state containing resource IDs
 /// * `universe_id` - The universe ID to include in the output
 /// * `output_path` - Path where the output file will be written
-pub fn generate_config(state: &SyncState, universe_id: u64, output_path: &str) -> Result<()> {
-    let path = Path::new(output_path);
+pub fn generate_config(state: &SyncState, universe_id: u64, output_path: &Path) -> Result<()> {
+    let path = output_path;
     let content = generate_luau_content(state, universe_id);
 
     // Create parent directories if they don't exist
@@ -26,7 +26,7 @@ pub fn generate_config(state: &SyncState, universe_id: u64, output_path: &str) -
     }
 
     std::fs::write(path, &content)?;
-    info!("Generated config file: {}", output_path);
+    info!("Generated config file: {}", output_path.display());
 
     Ok(())
 }
@@ -73,6 +73,15 @@ fn generate_luau_content(state: &SyncState, universe_id: u64) -> String {
     output.push_str("\tIsEnabled: boolean?,\n");
     output.push_str("}\n\n");
 
+    output.push_str("export type Subscription = {\n");
+    output.push_str("\tId: string,\n");
+    output.push_str("\tName: string,\n");
+    output.push_str("\tDescription: string?,\n");
+    output.push_str("\tPriceTier: string?,\n");
+    output.push_str("\tPeriod: string?,\n");
+    output.push_str("\tIsActive: boolean?,\n");
+    output.push_str("}\n\n");
+
     output.push_str("return {\n");
 
     // Universe
@@ -163,7 +172,31 @@ fn generate_luau_content(state: &SyncState, universe_id: u64) -> String {
         }
         output.push_str("\t\t},\n");
     }
-    output.push_str("\t} :: { Badge },\n");
+    output.push_str("\t} :: { Badge },\n\n");
+
+    // Subscriptions, with the `EXP-` IDs that MarketplaceService expects
+    output.push_str("\tSubscriptions = {\n");
+    let mut subscriptions: Vec<_> = state.subscriptions.iter().collect();
+    subscriptions.sort_by_key(|(id, _)| *id);
+    for (id, resource) in subscriptions {
+        output.push_str("\t\t{\n");
+        output.push_str(&format!("\t\t\tId = \"EXP-{}\",\n", id));
+        output.push_str(&format!("\t\t\tName = \"{}\",\n", escape_luau_string(&resource.name)));
+        if let Some(ref description) = resource.description {
+            output.push_str(&format!("\t\t\tDescription = \"{}\",\n", escape_luau_string(description)));
+        }
+        if let Some(ref price_tier) = resource.price_tier {
+            output.push_str(&format!("\t\t\tPriceTier = \"{}\",\n", escape_luau_string(price_tier)));
+        }
+        if let Some(ref period) = resource.period {
+            output.push_str(&format!("\t\t\tPeriod = \"{}\",\n", escape_luau_string(period)));
+        }
+        if let Some(is_active) = resource.is_enabled {
+            output.push_str(&format!("\t\t\tIsActive = {},\n", is_active));
+        }
+        output.push_str("\t\t},\n");
+    }
+    output.push_str("\t} :: { Subscription },\n");
 
     output.push_str("}\n");
 
@@ -192,6 +225,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::field_reassign_with_default)]
     fn test_generate_luau_content() {
         let mut state = SyncState::default();
         state.universe = Some(UniverseState {
@@ -212,6 +246,7 @@ mod tests {
                 is_enabled: None,
                 icon_hash: None,
                 icon_asset_id: None,
+                ..Default::default()
             },
         );
 
@@ -225,4 +260,19 @@ mod tests {
         assert!(content.contains(":: Universe"));
         assert!(content.contains("GamePass"));
     }
+
+    #[test]
+    fn test_subscriptions_use_exp_ids() {
+        let mut state = SyncState::default();
+        state.update_subscription(
+            5308545405541367925, "VIP Monthly".to_string(), None,
+            "4.99".to_string(), "monthly".to_string(), Some(true), None, None,
+        );
+
+        let content = generate_luau_content(&state, 1);
+        assert!(content.contains("Id = \"EXP-5308545405541367925\""));
+        assert!(content.contains("PriceTier = \"4.99\""));
+        assert!(content.contains("IsActive = true"));
+        assert!(content.contains(":: { Subscription }"));
+    }
 }
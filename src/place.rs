@@ -0,0 +1,191 @@
+//! Place versions: publishing with a record in the state, and rolling back.
+//!
+//! Every place file rblxsync publishes is recorded in the state with its version
+//! number, and a copy is kept in `.rblxsync/places/<place id>/` so that
+//! `place rollback` can publish it again. Only the most recent copies are kept.
+
+use crate::api::RobloxClient;
+use crate::backend::StateBackend;
+use crate::project::Project;
+use crate::state::{PublishedVersionState, SyncState};
+use anyhow::{anyhow, Context, Result};
+use log::{info, warn};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Directory holding copies of published place files, relative to the project root
+pub const ARTIFACTS_DIR: &str = ".rblxsync/places";
+
+/// Copies kept per place; older ones are removed when a new version is published
+pub const RETAINED_ARTIFACTS: usize = 5;
+
+/// Copies of published place files, named `<place id>/<version>.<extension>`
+pub struct Artifacts {
+    dir: PathBuf,
+}
+
+impl Artifacts {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    pub fn for_project(project: &Project) -> Self {
+        Self::new(project.resolve(ARTIFACTS_DIR))
+    }
+
+    /// Keep a copy of `file` as published `version`, removing the oldest copies
+    pub fn retain(&self, place_id: u64, version: u64, file: &Path) -> Result<PathBuf> {
+        let dir = self.dir.join(place_id.to_string());
+        fs::create_dir_all(&dir).with_context(|| format!("Failed to create {:?}", dir))?;
+        let extension = file.extension().and_then(|e| e.to_str()).unwrap_or("rbxl");
+        let path = dir.join(format!("{}.{}", version, extension));
+        fs::copy(file, &path).with_context(|| format!("Failed to copy {:?} to {:?}", file, path))?;
+
+        let versions = self.versions(place_id)?;
+        for (_, old) in versions.iter().take(versions.len().saturating_sub(RETAINED_ARTIFACTS)) {
+            fs::remove_file(old).with_context(|| format!("Failed to remove {:?}", old))?;
+        }
+        Ok(path)
+    }
+
+    /// The retained copy of a version, if any
+    pub fn find(&self, place_id: u64, version: u64) -> Result<Option<PathBuf>> {
+        Ok(self.versions(place_id)?.into_iter().find(|(v, _)| *v == version).map(|(_, path)| path))
+    }
+
+    /// Retained copies of a place, oldest version first
+    fn versions(&self, place_id: u64) -> Result<Vec<(u64, PathBuf)>> {
+        let dir = self.dir.join(place_id.to_string());
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {:?}", dir)),
+        };
+        let mut versions = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if let Some(version) = path.file_stem().and_then(|s| s.to_str()).and_then(|s| s.parse().ok()) {
+                versions.push((version, path));
+            }
+        }
+        versions.sort();
+        Ok(versions)
+    }
+}
+
+/// Publish `file` as a new version of the place, record it in the state and keep
+/// a copy of it. Returns the new version number.
+#[allow(clippy::too_many_arguments)]
+pub async fn publish_version(
+    universe_id: u64,
+    place_id: u64,
+    file: &Path,
+    rollback_of: Option<u64>,
+    project: &Project,
+    state: &mut SyncState,
+    backend: &mut dyn StateBackend,
+    client: &RobloxClient,
+) -> Result<u64> {
+    let content = fs::read(file).with_context(|| format!("Failed to read place file {:?}", file))?;
+    let response = client.publish_place(universe_id, place_id, file).await?;
+    let version = response["versionNumber"].as_u64()
+        .ok_or_else(|| anyhow!("Publishing place {} returned no version number: {}", place_id, response))?;
+
+    state.places.entry(place_id).or_default().published_versions.insert(version, PublishedVersionState {
+        published_at: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        file_hash: format!("{:x}", Sha256::digest(&content)),
+        rollback_of,
+    });
+    backend.save(state).await?;
+
+    // The version is live either way; a missing copy only limits later rollbacks
+    if let Err(e) = Artifacts::for_project(project).retain(place_id, version, file) {
+        warn!("Failed to keep a copy of place {} version {}: {}", place_id, version, e);
+    }
+    Ok(version)
+}
+
+/// Print the versions of a place, marking those rblxsync published and those it
+/// still has a copy of
+pub async fn versions(project: &Project, state: &SyncState, place_id: u64, client: &RobloxClient) -> Result<()> {
+    let items = crate::commands::list_all(|token| client.list_place_versions(place_id, token)).await?;
+    let published = state.places.get(&place_id).map(|p| &p.published_versions);
+    let artifacts = Artifacts::for_project(project);
+
+    if items.is_empty() {
+        info!("Place {} has no versions", place_id);
+    }
+    for item in items {
+        // Versions are named `assets/<id>/versions/<number>`
+        let Some(version) = item["path"].as_str()
+            .and_then(|p| p.rsplit('/').next())
+            .and_then(|v| v.parse::<u64>().ok())
+            .or_else(|| item["versionNumber"].as_u64())
+        else { continue };
+        let created = item["createTime"].as_str().unwrap_or("-");
+        let source = match published.and_then(|p| p.get(&version)) {
+            Some(PublishedVersionState { rollback_of: Some(of), .. }) => format!("rblxsync (rollback to {})", of),
+            Some(_) => "rblxsync".to_string(),
+            None => "-".to_string(),
+        };
+        let retained = if artifacts.find(place_id, version)?.is_some() { "retained" } else { "" };
+        println!("{:<8} {:<22} {:<28} {}", version, created, source, retained);
+    }
+    Ok(())
+}
+
+/// Publish an earlier version of a place again, from the retained copy or from `file`
+#[allow(clippy::too_many_arguments)]
+pub async fn rollback(
+    universe_id: u64,
+    place_id: u64,
+    version: u64,
+    file: Option<&Path>,
+    project: &Project,
+    state: &mut SyncState,
+    backend: &mut dyn StateBackend,
+    client: &RobloxClient,
+    dry_run: bool,
+) -> Result<()> {
+    let path = match file {
+        Some(file) => project.resolve(file),
+        None => Artifacts::for_project(project).find(place_id, version)?.ok_or_else(|| anyhow!(
+            "No retained copy of place {} version {} (only the last {} published versions are kept); pass --file with a downloaded copy",
+            place_id, version, RETAINED_ARTIFACTS
+        ))?,
+    };
+    if !path.exists() {
+        return Err(anyhow!("File not found: {}", path.display()));
+    }
+
+    if dry_run {
+        info!("  [ROLLBACK] Place {} - would publish version {} from {}", place_id, version, path.display());
+        return Ok(());
+    }
+    let new_version = publish_version(universe_id, place_id, &path, Some(version), project, state, backend, client).await?;
+    info!("  [ROLLED BACK] Place {} - published version {} as version {}", place_id, version, new_version);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_artifacts_keep_the_latest_versions() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("place.rbxlx");
+        fs::write(&file, "<roblox/>").unwrap();
+
+        let artifacts = Artifacts::new(dir.path().join("artifacts"));
+        for version in 1..=RETAINED_ARTIFACTS as u64 + 2 {
+            artifacts.retain(7, version, &file).unwrap();
+        }
+        assert!(artifacts.find(7, 1).unwrap().is_none());
+        assert!(artifacts.find(7, 2).unwrap().is_none());
+        let latest = artifacts.find(7, RETAINED_ARTIFACTS as u64 + 2).unwrap().unwrap();
+        assert_eq!(latest.extension().unwrap(), "rbxlx");
+        assert_eq!(artifacts.versions(7).unwrap().len(), RETAINED_ARTIFACTS);
+    }
+}
@@ -0,0 +1,146 @@
+//! Project root resolution.
+//!
+//! Every relative path in the config (`assets_dir`, place files, `output_path`)
+//! and the lock file itself are resolved against the directory containing the
+//! config file, so `rblxsync -c game/rblxsync.yml` behaves the same no matter
+//! which working directory it is invoked from.
+
+use crate::config::ConfigFormat;
+use std::path::{Path, PathBuf};
+
+/// Default lock file name (without extension), created next to the config file.
+/// The extension follows the config format, e.g. `rblxsync.toml` pairs with
+/// `rblxsync-lock.toml`, unless a lock file in another format already exists.
+pub const LOCK_FILE_STEM: &str = "rblxsync-lock";
+
+#[derive(Debug, Clone)]
+pub struct Project {
+    /// Directory containing the config file
+    root: PathBuf,
+    /// Path to the config file as given on the command line
+    config_path: PathBuf,
+    /// Path to the lock file (defaults to `<root>/rblxsync-lock.<ext>`)
+    lock_file: PathBuf,
+}
+
+impl Project {
+    /// Create a project from a config path and an optional lock file override.
+    ///
+    /// The override is taken as-is (relative to the working directory, like any
+    /// other command line path); only the default lock file lives in the root.
+    pub fn new(config_path: &Path, lock_file: Option<&Path>) -> Self {
+        let root = match config_path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        let lock_file = match lock_file {
+            Some(path) => path.to_path_buf(),
+            None => {
+                let format = ConfigFormat::from_path(config_path).unwrap_or(ConfigFormat::Yaml);
+                default_lock_file(&root, format)
+            }
+        };
+
+        Self {
+            root,
+            config_path: config_path.to_path_buf(),
+            lock_file,
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn config_path(&self) -> &Path {
+        &self.config_path
+    }
+
+    pub fn lock_file(&self) -> &Path {
+        &self.lock_file
+    }
+
+    /// Resolve a config-relative path against the project root.
+    /// Absolute paths are returned unchanged.
+    pub fn resolve(&self, path: impl AsRef<Path>) -> PathBuf {
+        let path = path.as_ref();
+        if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            self.root.join(path)
+        }
+    }
+
+    /// Resolve an icon filename against the configured `assets_dir`.
+    pub fn asset_path(&self, assets_dir: &str, icon: &str) -> PathBuf {
+        self.resolve(assets_dir).join(icon)
+    }
+}
+
+/// `<root>/rblxsync-lock.<ext>` in the config's format. If only a lock file in
+/// another format exists (e.g. `rblxsync-lock.yml` after converting the config
+/// to TOML), that one is used, so its state is not silently left behind.
+fn default_lock_file(root: &Path, format: ConfigFormat) -> PathBuf {
+    let preferred = root.join(format!("{}.{}", LOCK_FILE_STEM, format.extension()));
+    if preferred.exists() {
+        return preferred;
+    }
+    [ConfigFormat::Yaml, ConfigFormat::Toml, ConfigFormat::Json].iter()
+        .map(|other| root.join(format!("{}.{}", LOCK_FILE_STEM, other.extension())))
+        .find(|path| path.exists())
+        .unwrap_or(preferred)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_root_is_config_parent() {
+        let project = Project::new(Path::new("game/rblxsync.yml"), None);
+        assert_eq!(project.root(), Path::new("game"));
+        assert_eq!(project.lock_file(), Path::new("game/rblxsync-lock.yml"));
+        assert_eq!(project.resolve("assets"), PathBuf::from("game/assets"));
+        assert_eq!(
+            project.asset_path("assets/icons/", "vip.png"),
+            PathBuf::from("game/assets/icons/vip.png")
+        );
+    }
+
+    #[test]
+    fn test_bare_config_name_uses_current_dir() {
+        let project = Project::new(Path::new("rblxsync.yml"), None);
+        assert_eq!(project.root(), Path::new("."));
+        assert_eq!(project.lock_file(), Path::new("./rblxsync-lock.yml"));
+    }
+
+    #[test]
+    fn test_lock_file_follows_config_format() {
+        let project = Project::new(Path::new("rblxsync.toml"), None);
+        assert_eq!(project.lock_file(), Path::new("./rblxsync-lock.toml"));
+
+        let project = Project::new(Path::new("game/rblxsync.json"), None);
+        assert_eq!(project.lock_file(), Path::new("game/rblxsync-lock.json"));
+    }
+
+    #[test]
+    fn test_existing_lock_file_in_another_format_is_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("rblxsync.toml");
+        std::fs::write(dir.path().join("rblxsync-lock.yml"), "version: 4\n").unwrap();
+        assert_eq!(Project::new(&config, None).lock_file(), dir.path().join("rblxsync-lock.yml"));
+
+        // Once a lock file in the config's format exists, it takes precedence
+        std::fs::write(dir.path().join("rblxsync-lock.toml"), "version = 4\n").unwrap();
+        assert_eq!(Project::new(&config, None).lock_file(), dir.path().join("rblxsync-lock.toml"));
+    }
+
+    #[test]
+    fn test_lock_file_override_and_absolute_paths() {
+        let project = Project::new(Path::new("game/rblxsync.yml"), Some(Path::new("ci/lock.yml")));
+        assert_eq!(project.lock_file(), Path::new("ci/lock.yml"));
+
+        let absolute = std::env::temp_dir().join("place.rbxl");
+        assert_eq!(project.resolve(&absolute), absolute);
+    }
+}
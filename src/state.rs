@@ -1,25 +1,279 @@
-use anyhow::Result;
+use crate::config::ConfigFormat;
+use anyhow::{anyhow, Context, Result};
+use log::info;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::path::Path;
 
+/// Current lock file schema version. Bump it and append a migration to
+/// `MIGRATIONS` whenever the serialized shape of `LockFile` or `SyncState` changes.
+pub const STATE_VERSION: u32 = 9;
+
+/// Section holding state migrated from a single-universe lock file, which did not
+/// record its universe. It is assigned to a universe with `rblxsync state claim`.
+const UNCLAIMED_SECTION: &str = "unclaimed";
+
+/// Upgrades a raw lock file by one version; `MIGRATIONS[i]` takes version `i` to `i + 1`
+type Migration = fn(&mut serde_json::Value) -> Result<()>;
+
+const MIGRATIONS: [Migration; STATE_VERSION as usize] = [
+    // 0 -> 1: unversioned lock files; only the `version` header is added
+    |_| Ok(()),
+    // 1 -> 2: resources gain optional timestamps and a payload hash. Nothing to
+    // convert, but older binaries would silently drop the new fields on save
+    |_| Ok(()),
+    // 2 -> 3: new `places` section; nothing to convert (see above)
+    |_| Ok(()),
+    // 3 -> 4: the state moves into a per-universe section under `universes`
+    |raw| {
+        let Some(root) = raw.as_object_mut() else { return Ok(()) };
+        let mut section = std::mem::take(root);
+        section.remove("version");
+        let has_state = section.values().any(|v| !(v.is_null() || v.as_object().is_some_and(|o| o.is_empty())));
+        if has_state {
+            root.insert("universes".to_string(), serde_json::json!({ UNCLAIMED_SECTION: section }));
+        }
+        Ok(())
+    },
+    // 4 -> 5: new `data_stores` section; nothing to convert
+    |_| Ok(()),
+    // 5 -> 6: places record the versions rblxsync published; nothing to convert
+    |_| Ok(()),
+    // 6 -> 7: places gain a name and description; nothing to convert
+    |_| Ok(()),
+    // 7 -> 8: new `user_restrictions` section; nothing to convert
+    |_| Ok(()),
+    // 8 -> 9: new `subscriptions` section; nothing to convert
+    |_| Ok(()),
+];
+
+/// Which section of the lock file a config reads and writes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateScope {
+    pub universe_id: u64,
+    pub environment: Option<String>,
+}
+
+impl StateScope {
+    pub fn new(universe_id: u64, environment: Option<String>) -> Self {
+        Self { universe_id, environment }
+    }
+
+    /// Key of the section in the lock file: `<universe id>` or `<universe id>/<environment>`
+    pub fn key(&self) -> String {
+        match &self.environment {
+            Some(environment) => format!("{}/{}", self.universe_id, environment),
+            None => self.universe_id.to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for StateScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.environment {
+            Some(environment) => write!(f, "universe {} ({})", self.universe_id, environment),
+            None => write!(f, "universe {}", self.universe_id),
+        }
+    }
+}
+
+/// The lock file: one `SyncState` section per universe (and environment), so
+/// several configs for different universes can share a directory and lock file.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct LockFile {
+    /// Lock file schema version (missing in files written before versioning)
+    #[serde(default)]
+    pub version: u32,
+    /// Sections keyed by `StateScope::key`
+    #[serde(default)]
+    pub universes: BTreeMap<String, SyncState>,
+    /// Version and content of a lock file upgraded in memory by `parse`; kept as
+    /// a backup by the save that first writes the upgrade
+    #[serde(skip)]
+    upgraded_from: Option<(u32, String)>,
+}
+
+impl Default for LockFile {
+    fn default() -> Self {
+        Self {
+            version: STATE_VERSION,
+            universes: BTreeMap::new(),
+            upgraded_from: None,
+        }
+    }
+}
+
+impl LockFile {
+    /// Load the lock file, returning an empty one if it does not exist.
+    /// The lock file may be YAML, TOML or JSON, detected from its extension.
+    ///
+    /// Lock files written by older versions are upgraded in memory only; the
+    /// file itself is rewritten, and the original kept, by the next `save`.
+    pub fn load(path: &Path) -> Result<Self> {
+        let format = ConfigFormat::from_path(path)?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read lock file at {:?}", path))?;
+        let (lock_file, _) = Self::parse(format, &content)
+            .with_context(|| format!("Failed to load lock file at {:?}", path))?;
+        Ok(lock_file)
+    }
+
+    /// Parse lock file content, running any migrations needed to bring it up to
+    /// `STATE_VERSION`. Returns the lock file and the version it was stored as.
+    pub fn parse(format: ConfigFormat, content: &str) -> Result<(Self, u32)> {
+        let mut raw: serde_json::Value = format.parse(content)?;
+        if raw.is_null() {
+            // Empty file
+            return Ok((Self::default(), STATE_VERSION));
+        }
+
+        let version = match raw.get("version") {
+            None => 0,
+            Some(v) => v.as_u64()
+                .and_then(|v| u32::try_from(v).ok())
+                .ok_or_else(|| anyhow!("invalid lock file version: {}", v))?,
+        };
+        if version > STATE_VERSION {
+            return Err(anyhow!(
+                "lock file has version {}, but this rblxsync only understands versions up to {}. \
+                 It was written by a newer rblxsync; upgrade rblxsync to use it.",
+                version, STATE_VERSION
+            ));
+        }
+
+        for (from, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+            migration(&mut raw).with_context(|| format!("Failed to migrate lock file from version {}", from))?;
+        }
+        if let Some(root) = raw.as_object_mut() {
+            root.insert("version".to_string(), STATE_VERSION.into());
+        }
+
+        let mut lock_file: Self = serde_json::from_value(raw)?;
+        if version < STATE_VERSION {
+            lock_file.upgraded_from = Some((version, content.to_string()));
+        }
+        Ok((lock_file, version))
+    }
+
+    /// If this lock file was upgraded from an older version and not saved since,
+    /// the suffix for its backup (`.v<old version>.bak`) and the original content
+    pub fn take_backup(&mut self) -> Option<(String, String)> {
+        self.upgraded_from.take().map(|(version, content)| (format!(".v{}.bak", version), content))
+    }
+
+    /// Save the lock file. The write is atomic, so an interrupted save leaves the
+    /// previous lock file intact rather than a truncated one. The first save of
+    /// an upgraded lock file keeps the original as `<lock file>.v<old version>.bak`.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let format = ConfigFormat::from_path(path)?;
+        if let Some((version, original)) = &self.upgraded_from {
+            let mut backup_name = path.file_name().unwrap_or_default().to_os_string();
+            backup_name.push(format!(".v{}.bak", version));
+            let backup_path = path.with_file_name(backup_name);
+            if !backup_path.exists() {
+                write_atomic(&backup_path, original.as_bytes())
+                    .with_context(|| format!("Failed to back up lock file to {:?}", backup_path))?;
+                info!(
+                    "Upgraded lock file {} to version {} (backup: {})",
+                    path.display(), STATE_VERSION, backup_path.display()
+                );
+            }
+        }
+        let content = format.serialize(self)?;
+        write_atomic(path, content.as_bytes())
+    }
+
+    /// The state for `scope`, or an empty state if it has none yet. Fails while
+    /// state migrated from a single-universe lock file is unclaimed, since it
+    /// may belong to this universe.
+    pub fn section(&self, scope: &StateScope) -> Result<SyncState> {
+        let key = scope.key();
+        if let Some(state) = self.universes.get(&key) {
+            if state.universe_id.is_some_and(|id| id != scope.universe_id) || state.environment != scope.environment {
+                return Err(anyhow!(
+                    "Lock file section '{}' records state for universe {}{}; refusing to apply it to {}",
+                    key,
+                    state.universe_id.map(|id| id.to_string()).unwrap_or_else(|| "?".to_string()),
+                    state.environment.as_deref().map(|e| format!(" ({})", e)).unwrap_or_default(),
+                    scope
+                ));
+            }
+            return Ok(state.clone().with_scope(scope));
+        }
+
+        if self.universes.contains_key(UNCLAIMED_SECTION) {
+            return Err(anyhow!(
+                "The lock file holds state from before it recorded universes, and no universe has claimed it. \
+                 If it belongs to {}, run `rblxsync state claim` with this config.",
+                scope
+            ));
+        }
+        Ok(SyncState::new(scope))
+    }
+
+    /// Assign the state migrated from a single-universe lock file to `scope`
+    pub fn claim(&mut self, scope: &StateScope) -> Result<SyncState> {
+        if self.universes.contains_key(&scope.key()) {
+            return Err(anyhow!("The lock file already has state for {}", scope));
+        }
+        let state = self.universes.remove(UNCLAIMED_SECTION)
+            .ok_or_else(|| anyhow!("The lock file has no unclaimed state"))?
+            .with_scope(scope);
+        self.universes.insert(scope.key(), state.clone());
+        Ok(state)
+    }
+
+    /// Store `state` in its section
+    pub fn set_section(&mut self, state: &SyncState) -> Result<()> {
+        let scope = state.scope()?;
+        self.universes.insert(scope.key(), state.clone());
+        Ok(())
+    }
+}
+
+/// The synced state of one universe (a section of the lock file). Maps are ordered
+/// by ID so the file is written the same way every time, keeping git diffs
+/// limited to what actually changed.
 #[derive(Debug, Default, Deserialize, Serialize, Clone)]
 pub struct SyncState {
+    /// Universe the state belongs to (missing in state migrated from a single-universe lock file)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub universe_id: Option<u64>,
+    /// Environment name from the config, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<String>,
     /// Universe settings state
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub universe: Option<UniverseState>,
     /// Game passes keyed by their Roblox ID
     #[serde(default)]
-    pub game_passes: HashMap<u64, ResourceState>,
+    pub game_passes: BTreeMap<u64, ResourceState>,
     /// Developer products keyed by their Roblox ID
     #[serde(default)]
-    pub developer_products: HashMap<u64, ResourceState>,
+    pub developer_products: BTreeMap<u64, ResourceState>,
     /// Badges keyed by their Roblox ID
     #[serde(default)]
-    pub badges: HashMap<u64, ResourceState>,
+    pub badges: BTreeMap<u64, ResourceState>,
+    /// Subscriptions keyed by the numeric part of their `EXP-` ID
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub subscriptions: BTreeMap<u64, ResourceState>,
+    /// Place settings keyed by place ID
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub places: BTreeMap<u64, PlaceState>,
+    /// Seeded DataStore entries keyed by store name, then `<scope>/<key>`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub data_stores: BTreeMap<String, BTreeMap<String, DataStoreEntryState>>,
+    /// Bans applied by rblxsync (see `UserRestrictionState::key`)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub user_restrictions: BTreeMap<String, UserRestrictionState>,
 }
 
+/// Universe settings as confirmed by Roblox in its API responses
 #[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
 pub struct UniverseState {
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -37,13 +291,114 @@ pub struct UniverseState {
     pub private_server_cost: Option<String>,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+/// Place settings as confirmed by Roblox, and the versions rblxsync published
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+pub struct PlaceState {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_players: Option<u32>,
+    /// Versions published by rblxsync, keyed by version number
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub published_versions: BTreeMap<u64, PublishedVersionState>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+pub struct PublishedVersionState {
+    pub published_at: String,
+    /// SHA-256 of the published place file
+    pub file_hash: String,
+    /// Version this one restored, for versions published by `place rollback`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rollback_of: Option<u64>,
+}
+
+/// A DataStore entry as last written by rblxsync
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+pub struct DataStoreEntryState {
+    /// SHA-256 of the value's canonical JSON (see `DataStoreEntryState::hash`)
+    pub hash: String,
+    /// Entry version returned by Roblox for the write
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Base64 MD5 of the written body, as Roblox reports it in `content-md5`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_md5: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_synced_at: Option<String>,
+}
+
+impl DataStoreEntryState {
+    /// Hash of a value with object keys sorted, so reordering keys in the config
+    /// does not count as a change
+    pub fn hash(value: &serde_json::Value) -> String {
+        use sha2::{Digest, Sha256};
+
+        fn canonical(value: &serde_json::Value) -> serde_json::Value {
+            match value {
+                serde_json::Value::Object(map) => {
+                    let sorted: BTreeMap<&String, &serde_json::Value> = map.iter().collect();
+                    serde_json::Value::Object(sorted.into_iter().map(|(k, v)| (k.clone(), canonical(v))).collect())
+                }
+                serde_json::Value::Array(items) => serde_json::Value::Array(items.iter().map(canonical).collect()),
+                other => other.clone(),
+            }
+        }
+        format!("{:x}", Sha256::digest(canonical(value).to_string().as_bytes()))
+    }
+}
+
+/// A ban (game join restriction) as confirmed by Roblox
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+pub struct UserRestrictionState {
+    pub user_id: u64,
+    /// Set for bans from a single place rather than the whole universe
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub place_id: Option<u64>,
+    /// Length of the ban in the API's format (e.g. `"604800s"`); permanent if unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub private_reason: Option<String>,
+    #[serde(default)]
+    pub exclude_alt_accounts: bool,
+    /// When the ban started, as reported by Roblox
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_time: Option<String>,
+}
+
+impl UserRestrictionState {
+    /// Key in the lock file: the user ID, or `<place id>/<user id>` for place bans
+    pub fn key(user_id: u64, place_id: Option<u64>) -> String {
+        match place_id {
+            Some(place_id) => format!("{}/{}", place_id, user_id),
+            None => user_id.to_string(),
+        }
+    }
+
+    /// Whether both describe the same ban, regardless of when it started
+    pub fn same_terms(&self, other: &Self) -> bool {
+        Self { start_time: None, ..self.clone() } == Self { start_time: None, ..other.clone() }
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
 pub struct ResourceState {
     pub name: String,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub price: Option<u64>,
+    /// Subscription price tier in USD, e.g. "4.99"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub price_tier: Option<String>,
+    /// Subscription renewal period, e.g. "monthly"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub period: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub is_for_sale: Option<bool>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -52,33 +407,203 @@ pub struct ResourceState {
     pub icon_hash: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub icon_asset_id: Option<u64>,
+    /// When the resource was created, as reported by Roblox (or when rblxsync created it)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<String>,
+    /// When rblxsync last created or updated the resource on Roblox
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_synced_at: Option<String>,
+    /// Roblox's `updated` timestamp as of the last sync, to detect changes made outside rblxsync
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote_updated_at: Option<String>,
+    /// SHA-256 of the canonical form of the last applied metadata (see `ResourceState::fingerprint`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload_hash: Option<String>,
+}
+
+impl ResourceState {
+    /// Hash of the synced fields (name, description, price, flags and icon), serialized
+    /// with sorted keys so equal metadata always hashes the same
+    pub fn fingerprint(&self) -> String {
+        use sha2::{Digest, Sha256};
+
+        let mut payload: BTreeMap<&str, serde_json::Value> = [
+            ("name", serde_json::json!(self.name)),
+            ("description", serde_json::json!(self.description)),
+            ("price", serde_json::json!(self.price)),
+            ("is_for_sale", serde_json::json!(self.is_for_sale)),
+            ("is_enabled", serde_json::json!(self.is_enabled)),
+            ("icon_asset_id", serde_json::json!(self.icon_asset_id)),
+        ].into_iter().collect();
+        // Subscription-only fields are left out when unset, so the hashes of
+        // other resources stay the same
+        if let Some(price_tier) = &self.price_tier {
+            payload.insert("price_tier", serde_json::json!(price_tier));
+        }
+        if let Some(period) = &self.period {
+            payload.insert("period", serde_json::json!(period));
+        }
+        let canonical = serde_json::to_string(&payload).unwrap_or_default();
+        format!("{:x}", Sha256::digest(canonical.as_bytes()))
+    }
+}
+
+/// The resource sections of the lock file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourceKind {
+    GamePass,
+    DeveloperProduct,
+    Badge,
+    Subscription,
+}
+
+impl ResourceKind {
+    pub const ALL: [ResourceKind; 4] = [ResourceKind::GamePass, ResourceKind::DeveloperProduct, ResourceKind::Badge, ResourceKind::Subscription];
+
+    /// Name of the section in the lock file and config
+    pub fn section(&self) -> &'static str {
+        match self {
+            ResourceKind::GamePass => "game_passes",
+            ResourceKind::DeveloperProduct => "developer_products",
+            ResourceKind::Badge => "badges",
+            ResourceKind::Subscription => "subscriptions",
+        }
+    }
+
+    /// Human-readable name, for messages
+    pub fn label(&self) -> &'static str {
+        match self {
+            ResourceKind::GamePass => "game pass",
+            ResourceKind::DeveloperProduct => "developer product",
+            ResourceKind::Badge => "badge",
+            ResourceKind::Subscription => "subscription",
+        }
+    }
+}
+
+impl std::str::FromStr for ResourceKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().replace('-', "_").as_str() {
+            "game_pass" | "game_passes" | "pass" | "passes" => Ok(ResourceKind::GamePass),
+            "developer_product" | "developer_products" | "product" | "products" => Ok(ResourceKind::DeveloperProduct),
+            "badge" | "badges" => Ok(ResourceKind::Badge),
+            "subscription" | "subscriptions" => Ok(ResourceKind::Subscription),
+            _ => Err(anyhow!("unknown resource type '{}' (expected game_pass, developer_product, badge or subscription)", s)),
+        }
+    }
 }
 
 impl SyncState {
-    pub fn load(project_root: &Path) -> Result<Self> {
-        let state_path = Self::get_state_path(project_root);
-        if !state_path.exists() {
-            return Ok(Self::default());
+    /// An empty state for `scope`
+    pub fn new(scope: &StateScope) -> Self {
+        Self::default().with_scope(scope)
+    }
+
+    fn with_scope(mut self, scope: &StateScope) -> Self {
+        self.universe_id = Some(scope.universe_id);
+        self.environment = scope.environment.clone();
+        self
+    }
+
+    /// The section this state belongs to
+    pub fn scope(&self) -> Result<StateScope> {
+        let universe_id = self.universe_id.ok_or_else(|| anyhow!("state does not record its universe"))?;
+        Ok(StateScope::new(universe_id, self.environment.clone()))
+    }
+
+    /// Load the state for `scope` from the given lock file, returning an empty
+    /// state if the file or its section does not exist.
+    pub fn load(state_path: &Path, scope: &StateScope) -> Result<Self> {
+        LockFile::load(state_path)?.section(scope)
+    }
+
+    /// Save the state into its section of the lock file, leaving the sections
+    /// of other universes as they are.
+    pub fn save(&self, state_path: &Path) -> Result<()> {
+        let mut lock_file = LockFile::load(state_path)?;
+        lock_file.set_section(self)?;
+        lock_file.save(state_path)
+    }
+
+    /// The tracked resources of one kind, keyed by Roblox ID
+    pub fn resources(&self, kind: ResourceKind) -> &BTreeMap<u64, ResourceState> {
+        match kind {
+            ResourceKind::GamePass => &self.game_passes,
+            ResourceKind::DeveloperProduct => &self.developer_products,
+            ResourceKind::Badge => &self.badges,
+            ResourceKind::Subscription => &self.subscriptions,
         }
+    }
 
-        let content = fs::read_to_string(&state_path)?;
-        let state: SyncState = serde_yaml::from_str(&content)?;
-        Ok(state)
+    pub fn resources_mut(&mut self, kind: ResourceKind) -> &mut BTreeMap<u64, ResourceState> {
+        match kind {
+            ResourceKind::GamePass => &mut self.game_passes,
+            ResourceKind::DeveloperProduct => &mut self.developer_products,
+            ResourceKind::Badge => &mut self.badges,
+            ResourceKind::Subscription => &mut self.subscriptions,
+        }
     }
 
-    pub fn save(&self, project_root: &Path) -> Result<()> {
-        let state_path = Self::get_state_path(project_root);
-        if let Some(parent) = state_path.parent() {
-            fs::create_dir_all(parent)?;
+    /// Find a resource by Roblox ID or by name (case-insensitive) and return (id, state)
+    pub fn find_resource(&self, kind: ResourceKind, name_or_id: &str) -> Option<(u64, &ResourceState)> {
+        let resources = self.resources(kind);
+        if let Some(found) = name_or_id.parse::<u64>().ok().and_then(|id| resources.get_key_value(&id)) {
+            return Some((*found.0, found.1));
         }
+        resources.iter()
+            .find(|(_, state)| state.name.to_lowercase() == name_or_id.to_lowercase())
+            .map(|(id, state)| (*id, state))
+    }
 
-        let content = serde_yaml::to_string(self)?;
-        fs::write(state_path, content)?;
-        Ok(())
+    /// Record the metadata of a resource, keeping the timestamps already recorded
+    /// for it and refreshing its payload hash
+    pub fn set_resource(&mut self, kind: ResourceKind, id: u64, mut resource: ResourceState) {
+        let resources = self.resources_mut(kind);
+        if let Some(existing) = resources.get(&id) {
+            resource.created_at = resource.created_at.or_else(|| existing.created_at.clone());
+            resource.last_synced_at = resource.last_synced_at.or_else(|| existing.last_synced_at.clone());
+            resource.remote_updated_at = resource.remote_updated_at.or_else(|| existing.remote_updated_at.clone());
+        }
+        resource.payload_hash = Some(resource.fingerprint());
+        resources.insert(id, resource);
+    }
+
+    /// The recorded state of a seeded DataStore entry
+    pub fn data_store_entry(&self, data_store: &str, scope: &str, key: &str) -> Option<&DataStoreEntryState> {
+        self.data_stores.get(data_store)?.get(&format!("{}/{}", scope, key))
     }
 
-    fn get_state_path(project_root: &Path) -> PathBuf {
-        project_root.join("rblxsync-lock.yml")
+    pub fn set_data_store_entry(&mut self, data_store: &str, scope: &str, key: &str, entry: DataStoreEntryState) {
+        self.data_stores.entry(data_store.to_string()).or_default().insert(format!("{}/{}", scope, key), entry);
+    }
+
+    /// Record the timestamps of a tracked resource after a sync.
+    ///
+    /// `remote_created`/`remote_updated` are the timestamps from Roblox's listing,
+    /// if known. `applied` means rblxsync just created or updated the resource,
+    /// which also moves Roblox's `updated` time; it is picked up again on the next
+    /// sync. Returns true if the resource was changed on Roblox since the last
+    /// sync by someone other than rblxsync.
+    pub fn record_sync(&mut self, kind: ResourceKind, id: u64, remote_created: Option<String>, remote_updated: Option<String>, applied: bool) -> bool {
+        let Some(resource) = self.resources_mut(kind).get_mut(&id) else { return false };
+        let now = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+
+        if resource.created_at.is_none() {
+            resource.created_at = remote_created.or_else(|| applied.then(|| now.clone()));
+        }
+        if applied {
+            resource.last_synced_at = Some(now);
+            resource.remote_updated_at = None;
+            return false;
+        }
+
+        let drifted = matches!((&resource.remote_updated_at, &remote_updated), (Some(stored), Some(current)) if stored != current);
+        if remote_updated.is_some() {
+            resource.remote_updated_at = remote_updated;
+        }
+        drifted
     }
 
     /// Find a game pass by name (case-insensitive) and return (id, state)
@@ -88,6 +613,7 @@ impl SyncState {
             .map(|(id, state)| (*id, state))
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn update_game_pass(
         &mut self, 
         id: u64, 
@@ -98,14 +624,14 @@ impl SyncState {
         icon_hash: Option<String>, 
         icon_asset_id: Option<u64>
     ) {
-        self.game_passes.insert(id, ResourceState { 
+        self.set_resource(ResourceKind::GamePass, id, ResourceState { 
             name, 
             description,
             price,
             is_for_sale,
-            is_enabled: None,
             icon_hash, 
-            icon_asset_id 
+            icon_asset_id,
+            ..Default::default()
         });
     }
     
@@ -125,14 +651,13 @@ impl SyncState {
         icon_hash: Option<String>, 
         icon_asset_id: Option<u64>
     ) {
-        self.developer_products.insert(id, ResourceState { 
+        self.set_resource(ResourceKind::DeveloperProduct, id, ResourceState { 
             name, 
             description,
             price,
-            is_for_sale: None,
-            is_enabled: None,
             icon_hash, 
-            icon_asset_id 
+            icon_asset_id,
+            ..Default::default()
         });
     }
 
@@ -152,34 +677,409 @@ impl SyncState {
         icon_hash: Option<String>, 
         icon_asset_id: Option<u64>
     ) {
-        self.badges.insert(id, ResourceState { 
+        self.set_resource(ResourceKind::Badge, id, ResourceState { 
             name, 
             description,
-            price: None,
-            is_for_sale: None,
             is_enabled,
             icon_hash, 
-            icon_asset_id 
+            icon_asset_id,
+            ..Default::default()
         });
     }
 
-    pub fn update_universe(
+    /// Find a subscription by name (case-insensitive) and return (id, state)
+    pub fn find_subscription_by_name(&self, name: &str) -> Option<(u64, &ResourceState)> {
+        self.subscriptions.iter()
+            .find(|(_, state)| state.name.to_lowercase() == name.to_lowercase())
+            .map(|(id, state)| (*id, state))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_subscription(
         &mut self,
-        name: Option<String>,
+        id: u64,
+        name: String,
         description: Option<String>,
-        genre: Option<String>,
-        playable_devices: Option<Vec<String>>,
-        max_players: Option<u32>,
-        private_server_cost: Option<String>,
+        price_tier: String,
+        period: String,
+        is_enabled: Option<bool>,
+        icon_hash: Option<String>,
+        icon_asset_id: Option<u64>
     ) {
-        self.universe = Some(UniverseState {
+        self.set_resource(ResourceKind::Subscription, id, ResourceState {
             name,
             description,
-            genre,
-            playable_devices,
-            max_players,
-            private_server_cost,
+            price_tier: Some(price_tier),
+            period: Some(period),
+            is_enabled,
+            icon_hash,
+            icon_asset_id,
+            ..Default::default()
+        });
+    }
+}
+
+
+/// Three-way merge of lock files, for when two branches both changed the lock file
+/// (typically each adding different resources). Returns the merged lock file and a
+/// description of every conflicting entry; conflicts keep `ours`.
+pub fn merge(base: &LockFile, ours: &LockFile, theirs: &LockFile) -> (LockFile, Vec<String>) {
+    let mut merged = LockFile::default();
+    let mut conflicts = Vec::new();
+    let keys: BTreeSet<&String> = ours.universes.keys().chain(theirs.universes.keys()).collect();
+    for key in keys {
+        let section = |lock_file: &LockFile| lock_file.universes.get(key).cloned().unwrap_or_default();
+        let (state, section_conflicts) = merge_state(&section(base), &section(ours), &section(theirs));
+        conflicts.extend(section_conflicts.into_iter().map(|c| format!("{}: {}", key, c)));
+        merged.universes.insert(key.clone(), state);
+    }
+    (merged, conflicts)
+}
+
+fn merge_state(base: &SyncState, ours: &SyncState, theirs: &SyncState) -> (SyncState, Vec<String>) {
+    let mut conflicts = Vec::new();
+
+    let universe = merge_value(&base.universe, &ours.universe, &theirs.universe)
+        .unwrap_or_else(|| {
+            conflicts.push("universe".to_string());
+            ours.universe.clone()
         });
+
+    // Both sides syncing the same metadata only differ in timestamps; keep the later sync
+    let resolve_resource = |o: &ResourceState, t: &ResourceState| {
+        (o.fingerprint() == t.fingerprint()).then(|| {
+            if t.last_synced_at > o.last_synced_at { t.clone() } else { o.clone() }
+        })
+    };
+
+    // Both sides publishing a place only adds versions; combine them if the settings agree
+    let resolve_place = |o: &PlaceState, t: &PlaceState| {
+        let settings = |p: &PlaceState| PlaceState { published_versions: BTreeMap::new(), ..p.clone() };
+        (settings(o) == settings(t)).then(|| {
+            let mut merged = o.clone();
+            for (version, published) in &t.published_versions {
+                merged.published_versions.entry(*version).or_insert_with(|| published.clone());
+            }
+            merged
+        })
+    };
+
+    let mut merged = SyncState {
+        universe_id: ours.universe_id.or(theirs.universe_id),
+        environment: ours.environment.clone().or_else(|| theirs.environment.clone()),
+        universe,
+        game_passes: BTreeMap::new(),
+        developer_products: BTreeMap::new(),
+        badges: BTreeMap::new(),
+        subscriptions: BTreeMap::new(),
+        places: merge_map("places", &base.places, &ours.places, &theirs.places, resolve_place, &mut conflicts),
+        data_stores: BTreeMap::new(),
+        user_restrictions: merge_map(
+            "user_restrictions", &base.user_restrictions, &ours.user_restrictions, &theirs.user_restrictions,
+            |o, t| o.same_terms(t).then(|| o.clone()), &mut conflicts,
+        ),
+    };
+    for kind in ResourceKind::ALL {
+        *merged.resources_mut(kind) = merge_map(
+            kind.section(), base.resources(kind), ours.resources(kind), theirs.resources(kind),
+            resolve_resource, &mut conflicts,
+        );
     }
+
+    let stores: BTreeSet<&String> = ours.data_stores.keys().chain(theirs.data_stores.keys()).collect();
+    for store in stores {
+        let entries = |state: &SyncState| state.data_stores.get(store).cloned().unwrap_or_default();
+        let resolve_entry = |o: &DataStoreEntryState, t: &DataStoreEntryState| {
+            (o.hash == t.hash).then(|| if t.last_synced_at > o.last_synced_at { t.clone() } else { o.clone() })
+        };
+        let merged_entries = merge_map(
+            &format!("data_stores.{}", store), &entries(base), &entries(ours), &entries(theirs),
+            resolve_entry, &mut conflicts,
+        );
+        if !merged_entries.is_empty() {
+            merged.data_stores.insert(store.clone(), merged_entries);
+        }
+    }
+    (merged, conflicts)
 }
 
+/// Three-way merge of a single value: `None` if both sides changed it differently
+fn merge_value<T: Clone + PartialEq>(base: &T, ours: &T, theirs: &T) -> Option<T> {
+    if ours == theirs || theirs == base {
+        Some(ours.clone())
+    } else if ours == base {
+        Some(theirs.clone())
+    } else {
+        None
+    }
+}
+
+fn merge_map<K: Ord + Clone + std::fmt::Display, T: Clone + PartialEq>(
+    section: &str,
+    base: &BTreeMap<K, T>,
+    ours: &BTreeMap<K, T>,
+    theirs: &BTreeMap<K, T>,
+    resolve: impl Fn(&T, &T) -> Option<T>,
+    conflicts: &mut Vec<String>,
+) -> BTreeMap<K, T> {
+    let ids: BTreeSet<K> = base.keys().chain(ours.keys()).chain(theirs.keys()).cloned().collect();
+    let mut merged = BTreeMap::new();
+    for id in ids {
+        let (b, o, t) = (base.get(&id).cloned(), ours.get(&id).cloned(), theirs.get(&id).cloned());
+        let value = merge_value(&b, &o, &t).or_else(|| match (&o, &t) {
+            (Some(o), Some(t)) => resolve(o, t).map(Some),
+            _ => None,
+        });
+        let value = value.unwrap_or_else(|| {
+            conflicts.push(format!("{}.{}", section, id));
+            o
+        });
+        if let Some(value) = value {
+            merged.insert(id, value);
+        }
+    }
+    merged
+}
+
+/// Write a file atomically: write a sibling temp file, flush it to disk, then
+/// rename it over the destination so readers never observe a partial file.
+pub(crate) fn write_atomic(path: &Path, content: &[u8]) -> Result<()> {
+    use std::io::Write;
+
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    fs::create_dir_all(parent)?;
+
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let tmp_path = parent.join(format!(".{}.{}.tmp", file_name, std::process::id()));
+    let result = (|| -> Result<()> {
+        let mut file = fs::File::create(&tmp_path)?;
+        file.write_all(content)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result?;
+
+    // Persist the rename itself; not supported on every platform, so best-effort
+    if let Ok(dir) = fs::File::open(parent) {
+        let _ = dir.sync_all();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scope() -> StateScope {
+        StateScope::new(1, None)
+    }
+
+    #[test]
+    fn test_save_and_load_all_formats() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut state = SyncState::new(&scope());
+        state.update_game_pass(123, "VIP".to_string(), None, Some(100), Some(true), None, None);
+        state.update_badge(456, "Welcome".to_string(), Some("Hi".to_string()), Some(true), None, None);
+
+        for ext in ["yml", "toml", "json"] {
+            let path = dir.path().join(format!("rblxsync-lock.{}", ext));
+            state.save(&path).unwrap_or_else(|e| panic!("{}: {:#}", ext, e));
+            let loaded = SyncState::load(&path, &scope()).unwrap_or_else(|e| panic!("{}: {:#}", ext, e));
+            assert_eq!(loaded.game_passes[&123].price, Some(100));
+            assert_eq!(loaded.badges[&456].name, "Welcome");
+        }
+
+        // Sections stay in ID order, not string order, in TOML too
+        state.update_game_pass(9, "Nine".to_string(), None, None, None, None, None);
+        let toml = ConfigFormat::Toml.serialize(&state).unwrap();
+        assert!(toml.find("[game_passes.9]").unwrap() < toml.find("[game_passes.123]").unwrap());
+        assert!(toml.find("[game_passes").unwrap() < toml.find("[badges").unwrap());
+    }
+
+    #[test]
+    fn test_save_is_atomic_and_leaves_no_temp_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("rblxsync-lock.yml");
+        let mut state = SyncState::new(&scope());
+
+        for id in 1..=3 {
+            state.update_badge(id, format!("Badge {}", id), None, None, None, None);
+            state.save(&path).unwrap();
+        }
+
+        assert_eq!(SyncState::load(&path, &scope()).unwrap().badges.len(), 3);
+        let entries: Vec<_> = fs::read_dir(path.parent().unwrap()).unwrap().collect();
+        assert_eq!(entries.len(), 1);
+    }
+
+    #[test]
+    fn test_unversioned_lock_file_is_migrated_with_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rblxsync-lock.yml");
+        let legacy = "game_passes:\n  123:\n    name: VIP\n    price: 100\n";
+        fs::write(&path, legacy).unwrap();
+
+        // Loading upgrades in memory only
+        let mut lock_file = LockFile::load(&path).unwrap();
+        assert_eq!(lock_file.universes[UNCLAIMED_SECTION].game_passes[&123].name, "VIP");
+        assert_eq!(fs::read_to_string(&path).unwrap(), legacy);
+        assert!(!dir.path().join("rblxsync-lock.yml.v0.bak").exists());
+
+        // Saving writes the upgrade and keeps the original
+        lock_file.save(&path).unwrap();
+        assert_eq!(fs::read_to_string(dir.path().join("rblxsync-lock.yml.v0.bak")).unwrap(), legacy);
+        let upgraded = fs::read_to_string(&path).unwrap();
+        assert!(upgraded.starts_with(&format!("version: {}", STATE_VERSION)));
+
+        // The migrated state is not taken over until a universe claims it
+        let err = lock_file.section(&scope()).unwrap_err();
+        assert!(err.to_string().contains("state claim"));
+        let state = lock_file.claim(&scope()).unwrap();
+        assert_eq!(state.game_passes[&123].name, "VIP");
+        assert_eq!(lock_file.universes.keys().collect::<Vec<_>>(), ["1"]);
+        assert!(lock_file.claim(&scope()).is_err());
+    }
+
+    #[test]
+    fn test_sections_per_universe_and_environment() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rblxsync-lock.yml");
+        let lobby = StateScope::new(100, None);
+        let tournament = StateScope::new(200, Some("staging".to_string()));
+
+        let mut state = SyncState::load(&path, &lobby).unwrap();
+        state.update_badge(1, "Lobby".to_string(), None, None, None, None);
+        state.save(&path).unwrap();
+        let mut state = SyncState::load(&path, &tournament).unwrap();
+        assert!(state.badges.is_empty());
+        state.update_badge(2, "Finals".to_string(), None, None, None, None);
+        state.save(&path).unwrap();
+
+        assert_eq!(SyncState::load(&path, &lobby).unwrap().badges.keys().collect::<Vec<_>>(), [&1]);
+        assert_eq!(SyncState::load(&path, &tournament).unwrap().badges.keys().collect::<Vec<_>>(), [&2]);
+        assert_eq!(LockFile::load(&path).unwrap().universes.keys().collect::<Vec<_>>(), ["100", "200/staging"]);
+
+        // A section recording a different universe is refused
+        let mut lock_file = LockFile::load(&path).unwrap();
+        let moved = lock_file.universes.remove("100").unwrap();
+        lock_file.universes.insert("300".to_string(), moved);
+        let err = lock_file.section(&StateScope::new(300, None)).unwrap_err();
+        assert!(err.to_string().contains("refusing"));
+    }
+
+    #[test]
+    fn test_find_resource_by_id_or_name() {
+        let mut state = SyncState::default();
+        state.update_badge(42, "Welcome".to_string(), None, Some(true), None, None);
+        state.update_badge(7, "42".to_string(), None, Some(true), None, None);
+
+        assert_eq!(state.find_resource(ResourceKind::Badge, "welcome").map(|(id, _)| id), Some(42));
+        // IDs take precedence over names
+        assert_eq!(state.find_resource(ResourceKind::Badge, "42").map(|(id, _)| id), Some(42));
+        assert_eq!(state.find_resource(ResourceKind::Badge, "7").map(|(id, _)| id), Some(7));
+        assert!(state.find_resource(ResourceKind::GamePass, "welcome").is_none());
+
+        assert_eq!("developer-products".parse::<ResourceKind>().unwrap(), ResourceKind::DeveloperProduct);
+        assert_eq!("subscriptions".parse::<ResourceKind>().unwrap(), ResourceKind::Subscription);
+        assert!("bundle".parse::<ResourceKind>().is_err());
+    }
+
+    #[test]
+    fn test_fingerprint_and_timestamps() {
+        let mut state = SyncState::default();
+        state.update_game_pass(1, "VIP".to_string(), None, Some(100), Some(true), None, None);
+        let hash = state.game_passes[&1].payload_hash.clone().unwrap();
+        assert_eq!(hash, state.game_passes[&1].fingerprint());
+
+        // Created by us: creation and sync times are recorded
+        assert!(!state.record_sync(ResourceKind::GamePass, 1, None, None, true));
+        let created_at = state.game_passes[&1].created_at.clone();
+        assert!(created_at.is_some());
+        assert_eq!(state.game_passes[&1].last_synced_at, created_at);
+
+        // Next run picks up Roblox's updated time without reporting drift
+        assert!(!state.record_sync(ResourceKind::GamePass, 1, None, Some("2026-01-01T00:00:00Z".to_string()), false));
+        assert!(!state.record_sync(ResourceKind::GamePass, 1, None, Some("2026-01-01T00:00:00Z".to_string()), false));
+        // Edited outside rblxsync
+        assert!(state.record_sync(ResourceKind::GamePass, 1, None, Some("2026-02-01T00:00:00Z".to_string()), false));
+
+        // Metadata updates keep timestamps and refresh the hash
+        state.update_game_pass(1, "VIP".to_string(), None, Some(150), Some(true), None, None);
+        assert_eq!(state.game_passes[&1].created_at, created_at);
+        assert_eq!(state.game_passes[&1].remote_updated_at.as_deref(), Some("2026-02-01T00:00:00Z"));
+        assert_ne!(state.game_passes[&1].payload_hash.as_deref(), Some(hash.as_str()));
+    }
+
+    #[test]
+    fn test_serialization_is_ordered_by_id() {
+        let mut state = SyncState::default();
+        for id in [30, 4, 1000, 12] {
+            state.update_badge(id, format!("Badge {}", id), None, None, None, None);
+        }
+        let yaml = ConfigFormat::Yaml.serialize(&state).unwrap();
+        let positions: Vec<usize> = ["\n  4:", "\n  12:", "\n  30:", "\n  1000:"].iter().map(|k| yaml.find(k).unwrap()).collect();
+        assert!(positions.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(yaml, ConfigFormat::Yaml.serialize(&state.clone()).unwrap());
+    }
+
+    #[test]
+    fn test_merge_combines_additions_and_reports_conflicts() {
+        let mut base = SyncState::default();
+        base.update_game_pass(1, "VIP".to_string(), None, Some(100), Some(true), None, None);
+
+        let mut ours = base.clone();
+        ours.update_game_pass(2, "Boost".to_string(), None, Some(50), Some(true), None, None);
+        ours.update_game_pass(1, "VIP".to_string(), None, Some(150), Some(true), None, None);
+        let mut theirs = base.clone();
+        theirs.update_badge(9, "Welcome".to_string(), None, Some(true), None, None);
+
+        let (merged, conflicts) = merge_state(&base, &ours, &theirs);
+        assert!(conflicts.is_empty());
+        assert_eq!(merged.game_passes.keys().copied().collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(merged.game_passes[&1].price, Some(150));
+        assert!(merged.badges.contains_key(&9));
+
+        theirs.update_game_pass(1, "VIP".to_string(), None, Some(200), Some(true), None, None);
+        let (merged, conflicts) = merge_state(&base, &ours, &theirs);
+        assert_eq!(conflicts, ["game_passes.1"]);
+        assert_eq!(merged.game_passes[&1].price, Some(150));
+    }
+
+    #[test]
+    fn test_data_store_entries_hash_and_merge() {
+        let a: serde_json::Value = serde_json::from_str(r#"{"price": 100, "items": [{"id": 1, "name": "Sword"}]}"#).unwrap();
+        let b: serde_json::Value = serde_json::from_str(r#"{"items": [{"name": "Sword", "id": 1}], "price": 100}"#).unwrap();
+        assert_eq!(DataStoreEntryState::hash(&a), DataStoreEntryState::hash(&b));
+        assert_ne!(DataStoreEntryState::hash(&a), DataStoreEntryState::hash(&serde_json::json!({"price": 100})));
+
+        let entry = |value: &serde_json::Value| DataStoreEntryState { hash: DataStoreEntryState::hash(value), ..Default::default() };
+        let base = SyncState::new(&scope());
+        let mut ours = base.clone();
+        ours.set_data_store_entry("Shop", "global", "catalog", entry(&a));
+        let mut theirs = base.clone();
+        theirs.set_data_store_entry("Flags", "global", "features", entry(&serde_json::json!({"trading": true})));
+
+        let (merged, conflicts) = merge_state(&base, &ours, &theirs);
+        assert!(conflicts.is_empty());
+        assert!(merged.data_store_entry("Shop", "global", "catalog").is_some());
+        assert!(merged.data_store_entry("Flags", "global", "features").is_some());
+
+        theirs.set_data_store_entry("Shop", "global", "catalog", entry(&serde_json::json!({"price": 5})));
+        let (_, conflicts) = merge_state(&base, &ours, &theirs);
+        assert_eq!(conflicts, ["data_stores.Shop.global/catalog"]);
+    }
+
+    #[test]
+    fn test_newer_lock_file_is_rejected() {
+        let content = format!("version: {}\n", STATE_VERSION + 1);
+        let err = LockFile::parse(ConfigFormat::Yaml, &content).unwrap_err();
+        assert!(err.to_string().contains("upgrade rblxsync"));
+    }
+}